@@ -1,14 +1,180 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use itertools::Itertools;
 use log::info;
-use ndarray::{Array1, Array2};
+use ndarray::{Array1, Array2, Array3};
 use plotly::common::Mode;
 use plotly::{Plot, Scatter};
-use scalib::ttest;
 use scasim::plot::{plot_max_t_values, plot_t_traces};
+use scasim::{LeakageModel, generate_power_trace, load_waveform, markers_to_time_indices};
 use std::fs::File;
 use std::path::{Path, PathBuf};
 
+/// Rijndael (AES) forward S-box.
+///
+/// The request asked to pull this from the `aes` crate, but `aes` does not
+/// expose its S-box: the table lives in a private module and, on targets with
+/// hardware AES, is replaced entirely by AES-NI intrinsics with no byte table to
+/// borrow. We therefore keep the canonical 256-entry table inline — it matches
+/// the one `aes` uses for its software (fixslice) backend.
+const AES_SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+/// Number of key bytes recovered for AES-128.
+const AES_KEY_BYTES: usize = 16;
+/// Number of candidate values per key byte.
+const KEY_CANDIDATES: usize = 256;
+
+/// Online accumulator for Correlation Power Analysis against AES-128.
+///
+/// Sums are kept incrementally across files so that, like the t-test fold, no
+/// more than one file's traces need to be resident at once. Per-sample sums of
+/// the measured power are shared across all key guesses, while the predicted
+/// Hamming-weight sums are indexed by `(key_byte, key_guess)` and the cross term
+/// by `(key_byte, key_guess, sample)`.
+struct CpaAcc {
+    samples: usize,
+    n: u64,
+    sum_x: Array1<f64>,
+    sum_x2: Array1<f64>,
+    sum_hw: Array2<f64>,
+    sum_hw2: Array2<f64>,
+    sum_hwx: Array3<f64>,
+}
+
+impl CpaAcc {
+    fn new(samples: usize) -> Self {
+        Self {
+            samples,
+            n: 0,
+            sum_x: Array1::zeros(samples),
+            sum_x2: Array1::zeros(samples),
+            sum_hw: Array2::zeros((AES_KEY_BYTES, KEY_CANDIDATES)),
+            sum_hw2: Array2::zeros((AES_KEY_BYTES, KEY_CANDIDATES)),
+            sum_hwx: Array3::zeros((AES_KEY_BYTES, KEY_CANDIDATES, samples)),
+        }
+    }
+
+    /// Fold a batch of traces (one row per trace) together with the matching
+    /// plaintext blocks (one 16-byte row per trace) into the running sums.
+    fn update(&mut self, traces: &Array2<f32>, plaintexts: &Array2<u8>) {
+        let (num_traces, samples) = traces.dim();
+        assert_eq!(
+            samples, self.samples,
+            "Inconsistent number of samples per trace: expected {}, found {samples}",
+            self.samples
+        );
+        assert_eq!(
+            plaintexts.dim(),
+            (num_traces, AES_KEY_BYTES),
+            "Expected one 16-byte plaintext block per trace"
+        );
+
+        for i in 0..num_traces {
+            let x = traces.row(i);
+            for (s, &v) in x.iter().enumerate() {
+                let v = v as f64;
+                self.sum_x[s] += v;
+                self.sum_x2[s] += v * v;
+            }
+            for byte in 0..AES_KEY_BYTES {
+                let pt = plaintexts[[i, byte]];
+                for guess in 0..KEY_CANDIDATES {
+                    let sbox_out = AES_SBOX[(pt ^ guess as u8) as usize];
+                    // Hamming-weight leakage model of the S-box output.
+                    let hw = sbox_out.count_ones() as f64;
+                    self.sum_hw[[byte, guess]] += hw;
+                    self.sum_hw2[[byte, guess]] += hw * hw;
+                    let mut hwx = self.sum_hwx.slice_mut(ndarray::s![byte, guess, ..]);
+                    for (s, &v) in x.iter().enumerate() {
+                        hwx[s] += hw * v as f64;
+                    }
+                }
+            }
+            self.n += 1;
+        }
+    }
+
+    /// Pearson correlation of the predicted Hamming weights against the measured
+    /// power at every sample, for a single `(key_byte, key_guess)` pair.
+    ///
+    /// Samples with zero variance yield `NaN` so the caller can skip them when
+    /// searching for the peak correlation.
+    fn correlation(&self, byte: usize, guess: usize) -> Array1<f64> {
+        let n = self.n as f64;
+        let sum_hw = self.sum_hw[[byte, guess]];
+        let sum_hw2 = self.sum_hw2[[byte, guess]];
+        let hw_var = n * sum_hw2 - sum_hw * sum_hw;
+        Array1::from_shape_fn(self.samples, |s| {
+            let sum_x = self.sum_x[s];
+            let x_var = n * self.sum_x2[s] - sum_x * sum_x;
+            let denom = hw_var * x_var;
+            if denom <= 0.0 {
+                f64::NAN
+            } else {
+                (n * self.sum_hwx[[byte, guess, s]] - sum_hw * sum_x) / denom.sqrt()
+            }
+        })
+    }
+
+    /// Recover the full 16-byte key. For each key byte the winning guess is the
+    /// one whose peak `|r|` over all samples is largest. Returns the recovered
+    /// key together with, for every byte, the winning guess and its correlation
+    /// trace over samples.
+    fn recover_key(&self) -> (Vec<u8>, Vec<(u8, Array1<f64>)>) {
+        let mut key = vec![0u8; AES_KEY_BYTES];
+        let mut per_byte = Vec::with_capacity(AES_KEY_BYTES);
+        for byte in 0..AES_KEY_BYTES {
+            let mut best_guess = 0u8;
+            let mut best_peak = f64::NEG_INFINITY;
+            let mut best_corr = Array1::zeros(self.samples);
+            for guess in 0..KEY_CANDIDATES {
+                let corr = self.correlation(byte, guess);
+                let peak = corr
+                    .iter()
+                    .filter_map(|&r| r.is_finite().then_some(r.abs()))
+                    .fold(f64::NEG_INFINITY, f64::max);
+                if peak > best_peak {
+                    best_peak = peak;
+                    best_guess = guess as u8;
+                    best_corr = corr;
+                }
+            }
+            key[byte] = best_guess;
+            per_byte.push((best_guess, best_corr));
+        }
+        (key, per_byte)
+    }
+
+    /// Peak `|r|` of each candidate guess for a given key byte, for plotting
+    /// correlation-vs-key-guess.
+    fn peaks_for_byte(&self, byte: usize) -> Vec<f64> {
+        (0..KEY_CANDIDATES)
+            .map(|guess| {
+                self.correlation(byte, guess)
+                    .iter()
+                    .filter_map(|&r| r.is_finite().then_some(r.abs()))
+                    .fold(0.0, f64::max)
+            })
+            .collect()
+    }
+}
+
 #[derive(Parser, Debug)]
 #[clap(version)]
 struct Args {
@@ -27,6 +193,29 @@ struct Args {
         action = clap::ArgAction::SetTrue,
     )]
     show_plots: bool,
+    #[arg(
+        long,
+        value_enum,
+        help = "image rendering backend; 'plotters' draws SVG/PNG without a headless browser",
+        default_value_t = RenderBackendArg::Plotly
+    )]
+    render_backend: RenderBackendArg,
+}
+
+/// CLI spelling of [`scasim::RenderBackendKind`].
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum RenderBackendArg {
+    Plotly,
+    Plotters,
+}
+
+impl From<RenderBackendArg> for scasim::RenderBackendKind {
+    fn from(arg: RenderBackendArg) -> Self {
+        match arg {
+            RenderBackendArg::Plotly => scasim::RenderBackendKind::Plotly,
+            RenderBackendArg::Plotters => scasim::RenderBackendKind::Plotters,
+        }
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -54,6 +243,121 @@ enum Commands {
         #[arg(long = "npz-list", value_name = "NPZ_LIST_PATH")]
         maybe_npz_list_path: Option<String>,
     },
+    #[clap(
+        name = "cpa",
+        about = "Recover AES-128 key bytes from accumulated NPZ traces using Correlation Power Analysis"
+    )]
+    Cpa {
+        #[arg(long = "filenames", value_name = "NPZ_FILE", num_args = 1..)]
+        maybe_filenames: Option<Vec<String>>,
+        #[arg(long = "npz-list", value_name = "NPZ_LIST_PATH")]
+        maybe_npz_list_path: Option<String>,
+    },
+    #[clap(
+        name = "gen-traces",
+        about = "Generate a labeled NPZ trace set from a waveform and a markers file"
+    )]
+    GenTraces {
+        #[arg(value_name = "WAVE_FILE", index = 1)]
+        filename: String,
+        /// Markers file: one `start_time end_time label` per line
+        #[arg(value_name = "MARKERS_FILE", index = 2)]
+        markers_file: String,
+        #[arg(value_name = "OUTPUT_NPZ", index = 3)]
+        output: String,
+        #[arg(
+            long,
+            help = "disable multi-threaded loading of the waveform and signals",
+            default_value_t = false
+        )]
+        single_thread: bool,
+        #[arg(
+            long,
+            help = "log progress of each phase while processing",
+            default_value_t = true
+        )]
+        show_progress: bool,
+        #[arg(
+            long,
+            help = "keep only samples at multiples of this clock period (decimation)",
+            value_name = "CLOCK_PERIOD"
+        )]
+        clock_period: Option<u64>,
+        #[arg(
+            long,
+            help = "truncate longer segments and zero-pad shorter ones to this many samples so they share a length (no resampling)",
+            value_name = "SAMPLES"
+        )]
+        samples: Option<usize>,
+        #[arg(
+            long,
+            help = "leakage model used to turn transitions into power",
+            value_enum,
+            default_value_t = ModelArg::Hd
+        )]
+        model: ModelArg,
+        #[arg(
+            long,
+            help = "static-power weight for the weighted model (coefficient of HW(new))",
+            default_value_t = 0.0
+        )]
+        static_weight: f32,
+        #[arg(
+            long,
+            help = "dynamic-power weight for the weighted model (coefficient of HD(prev,new))",
+            default_value_t = 1.0
+        )]
+        dynamic_weight: f32,
+    },
+}
+
+/// CLI selector for the [`LeakageModel`] used by `gen-traces`.
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum ModelArg {
+    /// Hamming distance (pure transition counting).
+    Hd,
+    /// Hamming weight of the new value.
+    Hw,
+    /// Weighted combination of static and dynamic power.
+    Weighted,
+}
+
+/// Collect NPZ input paths from either an explicit list of filenames or a list
+/// file (one path per line, resolved relative to the list file's directory).
+fn collect_npz_filenames(
+    maybe_filenames: Option<Vec<String>>,
+    maybe_npz_list_path: Option<String>,
+) -> Vec<PathBuf> {
+    if let Some(npz_list_path) = maybe_npz_list_path {
+        let root_path = PathBuf::from(&npz_list_path)
+            .parent()
+            .unwrap_or_else(|| {
+                panic!(
+                    "NPZ list path '{}' does not have a parent directory",
+                    npz_list_path
+                )
+            })
+            .to_owned();
+        std::fs::read_to_string(npz_list_path)
+            .expect("Failed to read npz list file")
+            .lines()
+            .map(|line| {
+                let trimmed = line.trim();
+                if trimmed.is_empty() {
+                    panic!("Empty line in npz list file");
+                }
+                let mut p = PathBuf::from(trimmed);
+                if !p.is_absolute() {
+                    p = root_path.join(p);
+                }
+                p
+            })
+            .collect_vec()
+    } else if let Some(filenames) = maybe_filenames {
+        filenames.into_iter().map(PathBuf::from).collect_vec()
+    } else {
+        panic!("No NPZ files provided. Please specify at least one NPZ file.");
+    }
 }
 
 fn main() -> miette::Result<()> {
@@ -162,97 +466,60 @@ fn main() -> miette::Result<()> {
                 panic!("No NPZ files provided. Please specify at least one NPZ file.");
             };
 
-            let mut samples_per_trace = 0;
             let mut max_t_values = vec![Vec::<f64>::new(); order];
-            let mut num_traces_so_far = vec![];
-
-            let mut maybe_ttacc: Option<ttest::Ttest> = None;
+            let mut num_traces_so_far = vec![0usize];
 
             if filenames.is_empty() {
                 panic!("No NPZ files provided. Please specify at least one NPZ file.");
             }
 
-            let t_values = filenames
+            // Stream the archives into the accumulator in bounded chunks rather
+            // than materializing every trace of every file up front.
+            let ttacc = scasim::ttest_over_npz_chunked(
+                &filenames,
+                order,
+                scasim::DEFAULT_TTEST_CHUNK_SIZE,
+                &None,
+            )
+            .expect("Failed to compute t-test values");
+
+            let t_values = ttacc.get_ttest();
+            // Total trace count across all archives, for the x-axis of the
+            // max-|t| vs traces plot (cheap central-directory scan, no trace data).
+            let total_traces: usize = filenames
                 .iter()
-                .fold(None, |_, filename| {
-                    // Load the NPZ file using a bufferred reader
+                .map(|filename| {
                     let file = File::open(filename).expect("Failed to open NPZ file");
-                    let reader = std::io::BufReader::new(file);
-
-                    // Parse the NPZ file
-                    info!("Processing file: {}", filename.display());
-                    let mut npz_reader =
-                        ndarray_npz::NpzReader::new(reader).expect("Failed to parse NPZ file");
-                    let labels: Array1<u16> = npz_reader
-                        .by_name("labels")
-                        .expect("Failed to find 'labels' in NPZ file");
-
-                    let traces: Vec<Array1<f32>> = npz_reader
+                    let mut npz_reader = ndarray_npz::NpzReader::new(std::io::BufReader::new(file))
+                        .expect("Failed to parse NPZ file");
+                    npz_reader
                         .names()
                         .expect("Failed to get names from NPZ file")
                         .iter()
-                        .filter_map(|name| {
-                            name.starts_with("trace_").then(|| {
-                                npz_reader
-                                    .by_name(name.as_str())
-                                    .expect(&format!("Failed to find '{}' in NPZ file", name))
-                            })
-                        })
-                        .collect();
-                    let num_traces = traces.len();
-
-                    let traces_array: Array2<f32> = Array2::from_shape_vec(
-                        (num_traces, traces[0].len()),
-                        traces.into_iter().flatten().collect(),
-                    )
-                    .expect("Failed to create traces array");
-
-                    num_traces_so_far.push(
-                        num_traces_so_far
-                            .last()
-                            .map_or(num_traces, |&last| last + num_traces),
-                    );
-
-                    if samples_per_trace == 0 {
-                        // Initialize samples_per_trace with the length of the first trace
-                        samples_per_trace = traces_array.shape()[1];
-                    } else if samples_per_trace != traces_array.shape()[1] {
-                        panic!(
-                            "Inconsistent number of samples per trace: expected {}, found {}",
-                            samples_per_trace,
-                            traces_array.shape()[1]
-                        );
-                    }
-
-                    if maybe_ttacc.is_none() {
-                        maybe_ttacc = Some(ttest::Ttest::new(samples_per_trace, order));
-                    }
-
-                    if let Some(ref mut ttacc) = maybe_ttacc {
-                        ttacc.update(traces_array.view(), labels.view());
-
-                        let t_values = ttacc.get_ttest();
-                        max_t_values
-                            .iter_mut()
-                            .zip(t_values.rows())
-                            .for_each(|(max_t, t_row)| {
-                                max_t.push(
-                                    t_row
-                                        .iter()
-                                        .filter_map(|&x| x.is_finite().then_some(x.abs()))
-                                        .max_by(|a, b| a.partial_cmp(b).unwrap())
-                                        .expect("Failed to find max t-value in current row"),
-                                );
-                            });
-                        Some(t_values)
-                    } else {
-                        panic!("Ttest accumulator is not initialized");
-                    }
+                        .filter(|name| name.starts_with("trace_"))
+                        .count()
                 })
-                .expect("Failed to compute t-test values");
+                .sum();
+            num_traces_so_far.push(total_traces);
+            max_t_values
+                .iter_mut()
+                .zip(t_values.rows())
+                .for_each(|(max_t, t_row)| {
+                    max_t.push(0.0);
+                    max_t.push(
+                        t_row
+                            .iter()
+                            .filter_map(|&x| x.is_finite().then_some(x.abs()))
+                            .max_by(|a, b| a.partial_cmp(b).unwrap())
+                            .expect("Failed to find max t-value in current row"),
+                    );
+                });
 
             let t_threshold = Some(4.5);
 
+            let mut backend =
+                scasim::RenderBackendKind::from(args.render_backend).build(&plots_config)?;
+
             plot_t_traces(
                 t_values,
                 t_threshold,
@@ -260,6 +527,7 @@ fn main() -> miette::Result<()> {
                 output_dir,
                 args.show_plots,
                 &plots_config,
+                backend.as_mut(),
             )?;
 
             assert!(max_t_values.len() == order);
@@ -272,8 +540,231 @@ fn main() -> miette::Result<()> {
                 output_dir,
                 args.show_plots,
                 &plots_config,
+                backend.as_mut(),
             )?;
         }
+        Commands::Cpa {
+            maybe_filenames,
+            maybe_npz_list_path,
+        } => {
+            let filenames = collect_npz_filenames(maybe_filenames, maybe_npz_list_path);
+            if filenames.is_empty() {
+                panic!("No NPZ files provided. Please specify at least one NPZ file.");
+            }
+
+            let mut maybe_acc: Option<CpaAcc> = None;
+
+            for filename in &filenames {
+                info!("Processing file: {}", filename.display());
+                let file = File::open(filename).expect("Failed to open NPZ file");
+                let reader = std::io::BufReader::new(file);
+                let mut npz_reader =
+                    ndarray_npz::NpzReader::new(reader).expect("Failed to parse NPZ file");
+
+                let plaintexts: Array2<u8> = npz_reader
+                    .by_name("plaintexts")
+                    .expect("Failed to find 'plaintexts' in NPZ file");
+
+                let traces: Vec<Array1<f32>> = npz_reader
+                    .names()
+                    .expect("Failed to get names from NPZ file")
+                    .iter()
+                    .filter_map(|name| {
+                        name.starts_with("trace_").then(|| {
+                            npz_reader
+                                .by_name(name.as_str())
+                                .expect(&format!("Failed to find '{}' in NPZ file", name))
+                        })
+                    })
+                    .collect();
+                let num_traces = traces.len();
+                assert!(num_traces > 1, "Number of traces must be greater than 1");
+                let traces_array: Array2<f32> = Array2::from_shape_vec(
+                    (num_traces, traces[0].len()),
+                    traces.into_iter().flatten().collect(),
+                )
+                .expect("Failed to create traces array");
+
+                let acc = maybe_acc
+                    .get_or_insert_with(|| CpaAcc::new(traces_array.shape()[1]));
+                acc.update(&traces_array, &plaintexts);
+            }
+
+            let acc = maybe_acc.expect("Failed to accumulate any traces");
+            let (key, per_byte) = acc.recover_key();
+
+            println!(
+                "Recovered AES-128 key: {}",
+                key.iter().map(|b| format!("{b:02x}")).collect::<String>()
+            );
+
+            // Correlation vs key guess (peak |r| for every candidate, per byte).
+            let mut guess_plot = Plot::new();
+            guess_plot.set_configuration(plots_config.clone());
+            for byte in 0..AES_KEY_BYTES {
+                let peaks = acc.peaks_for_byte(byte);
+                let trace = Scatter::new((0..KEY_CANDIDATES).collect_vec(), peaks)
+                    .mode(Mode::Lines)
+                    .name(format!("byte {byte}"))
+                    .line(plotly::common::Line::new().width(1.0).auto_color_scale(true));
+                guess_plot.add_trace(trace);
+            }
+            guess_plot.set_layout(
+                plotly::Layout::new()
+                    .title("CPA: max |correlation| vs key guess")
+                    .x_axis(plotly::layout::Axis::new().title("Key guess"))
+                    .y_axis(plotly::layout::Axis::new().title("max |r|")),
+            );
+            let guess_path = output_dir.join("cpa_correlation_vs_guess.html");
+            info!("Writing CPA guess plot to {}", guess_path.display());
+            guess_plot.write_html(guess_path);
+
+            // Correlation vs sample for the winning guess of every key byte.
+            let mut sample_plot = Plot::new();
+            sample_plot.set_configuration(plots_config.clone());
+            for (byte, (guess, corr)) in per_byte.iter().enumerate() {
+                let abs_corr = corr.map(|r| if r.is_finite() { r.abs() } else { 0.0 });
+                let trace = Scatter::from_array(
+                    Array1::range(0., abs_corr.len() as f32, 1.0),
+                    abs_corr,
+                )
+                .mode(Mode::Lines)
+                .name(format!("byte {byte} = {guess:02x}"))
+                .line(plotly::common::Line::new().width(1.0).auto_color_scale(true));
+                sample_plot.add_trace(trace);
+            }
+            sample_plot.set_layout(
+                plotly::Layout::new()
+                    .title("CPA: |correlation| vs sample for the recovered key")
+                    .x_axis(plotly::layout::Axis::new().title("Sample"))
+                    .y_axis(plotly::layout::Axis::new().title("|r|")),
+            );
+            let sample_path = output_dir.join("cpa_correlation_vs_sample.html");
+            info!("Writing CPA sample plot to {}", sample_path.display());
+            sample_plot.write_html(sample_path);
+
+            if args.show_plots {
+                guess_plot.show();
+                sample_plot.show();
+            }
+        }
+        Commands::GenTraces {
+            filename,
+            markers_file,
+            output,
+            single_thread,
+            show_progress,
+            clock_period,
+            samples,
+            model,
+            static_weight,
+            dynamic_weight,
+        } => {
+            let model = match model {
+                ModelArg::Hd => LeakageModel::HammingDistance,
+                ModelArg::Hw => LeakageModel::HammingWeight,
+                ModelArg::Weighted => LeakageModel::Weighted {
+                    static_weight,
+                    dynamic_weight,
+                },
+            };
+            // Parse the markers file into (start_time, end_time, label) tuples.
+            let meta_markers: Vec<(u64, u64, u16)> = std::fs::read_to_string(&markers_file)
+                .expect("Failed to read markers file")
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| {
+                    let (start, end, label) = line
+                        .split_whitespace()
+                        .collect_tuple()
+                        .expect("Each marker line must have: start_time end_time label");
+                    (
+                        start.parse().expect("Invalid start_time"),
+                        end.parse().expect("Invalid end_time"),
+                        label.parse().expect("Invalid label"),
+                    )
+                })
+                .collect();
+            assert!(!meta_markers.is_empty(), "No markers found in markers file");
+
+            // Forward progress snapshots to a simple logging consumer.
+            let (progress_tx, progress_rx) =
+                crossbeam_channel::unbounded::<scasim::ProgressData>();
+            let progress_sender: scasim::ProgressSender = show_progress.then_some(progress_tx);
+            let consumer = show_progress.then(|| {
+                std::thread::spawn(move || {
+                    for p in progress_rx.iter() {
+                        info!("[{:?}] {}/{} {}", p.phase, p.done, p.total, p.filename);
+                    }
+                })
+            });
+
+            let (signals, time_table) = load_waveform(
+                &filename,
+                !single_thread,
+                show_progress,
+                &progress_sender,
+                &None,
+            )
+            .expect("Failed to load waveform!");
+
+            // A clock period of 0 would make the decimation closure compute
+            // `t % 0` and panic; treat it as "no decimation", same as omitting
+            // the flag.
+            let clock_period = clock_period.filter(|&cp| cp != 0);
+            let cp = clock_period.unwrap_or_default();
+            let (time_table, power_table) = generate_power_trace(
+                &signals,
+                &time_table,
+                |(t, _)| *t % cp == 0,
+                clock_period.is_some(),
+                model,
+                &progress_sender,
+                &None,
+            )
+            .expect("Failed to generate power trace!");
+
+            let time_indices_and_labels = markers_to_time_indices(&meta_markers, &time_table);
+            let max_len = time_indices_and_labels
+                .iter()
+                .map(|(lo, hi, _)| hi - lo)
+                .max()
+                .unwrap_or(0);
+            // All segments share the requested sample count, or the longest
+            // segment when none is given, so the t-test can consume them.
+            let samples_per_trace = samples.unwrap_or(max_len);
+
+            let num_traces = time_indices_and_labels.len();
+            let mut traces = Array2::<f32>::zeros((num_traces, samples_per_trace));
+            let mut labels = Array1::<u16>::zeros(num_traces);
+            for (i, (lo, hi, label)) in time_indices_and_labels.into_iter().enumerate() {
+                labels[i] = label;
+                let copy_len = (hi - lo).min(samples_per_trace);
+                traces
+                    .slice_mut(ndarray::s![i, ..copy_len])
+                    .assign(&Array1::from_vec(power_table[lo..lo + copy_len].to_vec()));
+            }
+
+            drop(progress_sender);
+            if let Some(handle) = consumer {
+                handle.join().unwrap();
+            }
+
+            info!(
+                "Writing {} traces of {} samples to {}",
+                num_traces, samples_per_trace, output
+            );
+            let mut npz = ndarray_npz::NpzWriter::new_compressed(
+                File::create(&output).expect("Failed to create npz file"),
+            );
+            for (tidx, trace) in traces.outer_iter().enumerate() {
+                npz.add_array(format!("trace_{tidx}"), &trace)
+                    .expect("Failed to add trace array to npz");
+            }
+            npz.add_array("labels", &labels)
+                .expect("Failed to add labels array to npz");
+            npz.finish().expect("Failed to finish writing npz file");
+        }
     }
     Ok(())
 }
@@ -1,15 +1,12 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use itertools::Itertools;
 use log::*;
 use ndarray::{Array1, Array2, s};
 use ndarray_npz::{NpzReader, NpzWriter};
-use plotly::plotly_static;
-use rayon::prelude::{IntoParallelIterator, ParallelIterator};
-use scalib::ttest;
 use scasim::plot::*;
 use scasim::*;
 use std::fs::File;
-use std::io::Read;
+use std::io::{IsTerminal, Read};
 use std::path::{Path, PathBuf};
 
 #[derive(Parser, Debug)]
@@ -69,6 +66,102 @@ struct Args {
         default_value = ""
     )]
     ttest_output_dir: String,
+    #[arg(
+        long,
+        help = "maximum number of trace batches queued in flight between the loader workers and the t-test consumer (defaults to the worker count)",
+        value_name = "MAX_IN_FLIGHT"
+    )]
+    max_in_flight: Option<usize>,
+    #[arg(
+        long,
+        help = "split a file's trace set into batches of at most this many traces to bound per-batch memory",
+        value_name = "MAX_SAMPLES_PER_BATCH"
+    )]
+    max_samples_per_batch: Option<usize>,
+    #[arg(
+        long,
+        value_enum,
+        help = "codec for the per-trace store; the small t-test result archive stays deflate-compressed regardless",
+        default_value_t = CompressionArg::None
+    )]
+    compression: CompressionArg,
+    #[arg(
+        long,
+        help = "compression level passed to the selected codec (meaning is codec-specific)",
+        value_name = "LEVEL",
+        default_value_t = scasim::DEFAULT_COMPRESSION_LEVEL
+    )]
+    compression_level: i32,
+    #[arg(
+        long,
+        value_enum,
+        help = "image rendering backend; 'plotters' draws SVG/PNG without a headless browser",
+        default_value_t = RenderBackendArg::Plotly
+    )]
+    render_backend: RenderBackendArg,
+    #[arg(
+        long = "export",
+        help = "Also export t-test results to standard .trs/.npy formats alongside the NPZ",
+        default_value_t = false
+    )]
+    export: bool,
+    #[arg(
+        long,
+        value_enum,
+        help = "leakage detector driving the fold: 't-test' (fixed-vs-random TVLA) or 'cpa' (correlation against the label)",
+        default_value_t = DetectorArg::TTest
+    )]
+    detector: DetectorArg,
+}
+
+/// CLI spelling of [`scasim::DetectorKind`].
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum DetectorArg {
+    TTest,
+    Cpa,
+}
+
+impl From<DetectorArg> for scasim::DetectorKind {
+    fn from(arg: DetectorArg) -> Self {
+        match arg {
+            DetectorArg::TTest => scasim::DetectorKind::TTest,
+            DetectorArg::Cpa => scasim::DetectorKind::Cpa,
+        }
+    }
+}
+
+/// CLI spelling of [`scasim::Compression`].
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum CompressionArg {
+    None,
+    Deflate,
+    Zstd,
+}
+
+/// CLI spelling of [`scasim::RenderBackendKind`].
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum RenderBackendArg {
+    Plotly,
+    Plotters,
+}
+
+impl From<RenderBackendArg> for scasim::RenderBackendKind {
+    fn from(arg: RenderBackendArg) -> Self {
+        match arg {
+            RenderBackendArg::Plotly => scasim::RenderBackendKind::Plotly,
+            RenderBackendArg::Plotters => scasim::RenderBackendKind::Plotters,
+        }
+    }
+}
+
+impl From<CompressionArg> for scasim::Compression {
+    fn from(arg: CompressionArg) -> Self {
+        match arg {
+            CompressionArg::None => scasim::Compression::None,
+            CompressionArg::Deflate => scasim::Compression::Deflate,
+            CompressionArg::Zstd => scasim::Compression::Zstd,
+        }
+    }
 }
 
 fn get_metadata<P: AsRef<Path>>(
@@ -120,6 +213,296 @@ fn cut_trace(
     (all_traces, trace_labels)
 }
 
+/// Version tag for the power-model generation logic. Bump this whenever the
+/// meaning of a generated trace store changes so older caches are invalidated.
+const POWER_MODEL_VERSION: u32 = 1;
+
+/// Build the manifest describing the inputs a generated cache depends on. Two
+/// caches are interchangeable iff their manifests are equal, regardless of file
+/// timestamps.
+fn expected_manifest(
+    clock_period: Option<u64>,
+    meta_markers: &[(u64, u64, u16)],
+    model: LeakageModel,
+) -> serde_json::Value {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    meta_markers.hash(&mut hasher);
+    let markers_hash = hasher.finish();
+    // The decimation predicate keeps samples at multiples of the clock period.
+    let time_filter = match clock_period {
+        Some(cp) => format!("mod:{cp}"),
+        None => "none".to_string(),
+    };
+    serde_json::json!({
+        "clock_period": clock_period,
+        "markers_hash": markers_hash,
+        "num_markers": meta_markers.len(),
+        "time_filter": time_filter,
+        "power_model": format!("{model:?}"),
+        "power_model_version": POWER_MODEL_VERSION,
+    })
+}
+
+/// Compare an expected manifest with the one stored beside a cache. Returns the
+/// name of the first field that differs (or `"<missing manifest>"`), or `None`
+/// when they match.
+fn manifest_mismatch(expected: &serde_json::Value, sidecar: &Path) -> Option<String> {
+    let stored: serde_json::Value = match std::fs::read_to_string(sidecar) {
+        Ok(s) => serde_json::from_str(&s).unwrap_or(serde_json::Value::Null),
+        Err(_) => return Some("<missing manifest>".to_string()),
+    };
+    for (key, value) in expected.as_object().unwrap() {
+        if stored.get(key) != Some(value) {
+            return Some(key.clone());
+        }
+    }
+    None
+}
+
+fn parse_markers(metadata_json: &serde_json::Value) -> Vec<(u64, u64, u16)> {
+    metadata_json
+        .get("markers")
+        .map(|v| {
+            v.as_array()
+                .unwrap()
+                .into_iter()
+                .map(|e| {
+                    let (start_time, end_time, label) = e
+                        .as_array()
+                        .unwrap()
+                        .into_iter()
+                        .map(|i| i.as_u64().unwrap())
+                        .collect_tuple()
+                        .unwrap();
+                    (start_time, end_time, label as u16)
+                })
+                .collect_vec()
+        })
+        .expect("markers not found in metadata")
+}
+
+/// Load (or regenerate) the trace set for a single metadata file, returning the
+/// dense trace matrix and its labels. Kept free of shared state so it can run on
+/// a worker thread feeding the bounded-memory pipeline.
+fn process_metadata_file(
+    metadata_path: &Path,
+    single_thread: bool,
+    show_progress: bool,
+    use_existing_flag: bool,
+    npz_filename: &str,
+    compression: scasim::Compression,
+    compression_level: i32,
+    stage: Option<&indicatif::ProgressBar>,
+    progress: &ProgressSender,
+    stop: &StopReceiver,
+) -> Option<(Array2<f32>, Array1<u16>)> {
+    // Update the caller-supplied per-file bar with the current stage, if any.
+    let set_stage = |msg: &str| {
+        if let Some(bar) = stage {
+            bar.set_message(format!(
+                "{}: {msg}",
+                metadata_path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            ));
+        }
+    };
+    set_stage("reading metadata");
+    if !metadata_path.exists() {
+        log::error!("Metadata file '{}' does not exist!", metadata_path.display());
+        return None;
+    }
+
+    let metadata_json = get_metadata(
+        metadata_path,
+        metadata_path.extension().map_or(false, |ext| ext == "gz"),
+    )
+    .expect("Failed to load metadata!");
+
+    let parent_folder_path = metadata_path
+        .parent()
+        .expect("Failed to get parent folder of metadata file")
+        .to_path_buf();
+
+    let trace_filename = metadata_json
+        .get("trace_filename")
+        .and_then(|v| v.as_str())
+        .expect("trace_filename not found in metadata");
+
+    let trace_file_path = parent_folder_path.join(trace_filename);
+
+    let npz_path = parent_folder_path.join(npz_filename);
+    let store_path = parent_folder_path.join("traces.store");
+    let manifest_path = parent_folder_path.join("traces.manifest.json");
+
+    // A clock period of 0 would make the decimation closure compute `t % 0` and
+    // panic; treat it as "no decimation", same as an absent field.
+    let clock_period = metadata_json
+        .get("clock_period")
+        .and_then(|v| v.as_u64())
+        .filter(|&cp| cp != 0);
+    let cp = clock_period.unwrap_or_default();
+    let meta_markers = parse_markers(&metadata_json);
+    let model = LeakageModel::default();
+    let manifest = expected_manifest(clock_period, &meta_markers, model);
+
+    // Prefer the memory-mapped trace store over the per-trace NPZ cache, but only
+    // reuse it when its manifest matches the current generation parameters. The
+    // manifest guards against stale caches when the FST is untouched but the
+    // power model, clock period, decimation or markers change.
+    if use_existing_flag && store_path.exists() {
+        match manifest_mismatch(&manifest, &manifest_path) {
+            Some(field) => log::info!(
+                "Regenerating {}: cache manifest differs on '{}'",
+                store_path.display(),
+                field
+            ),
+            None => {
+                println!(
+                    "Using existing traces and labels from {}",
+                    store_path.display()
+                );
+                let store =
+                    scasim::open_trace_store(&store_path).expect("Failed to open trace store");
+                return Some((store.traces().to_owned(), store.labels().to_owned()));
+            }
+        }
+    }
+
+    // The legacy per-trace NPZ cache predates the manifest, so gate its reuse on
+    // the same content hash the store uses. A stale `traces.npz` left over from a
+    // different model, clock period, decimation or marker set must not be silently
+    // reused just because it happens to be newer than the FST.
+    let npz_manifest_ok = manifest_mismatch(&manifest, &manifest_path).is_none();
+    let use_existing = if use_existing_flag && npz_manifest_ok && npz_path.exists() {
+        if !trace_file_path.exists() {
+            true
+        } else {
+            // Check if the npz file is older than the trace file
+            let npz_modified = std::fs::metadata(&npz_path).and_then(|m| m.modified());
+            let trace_modified = std::fs::metadata(&trace_file_path).and_then(|m| m.modified());
+            if let (Ok(npz_modified), Ok(trace_modified)) = (npz_modified, trace_modified) {
+                // Use existing if npz file is newer than trace file
+                npz_modified > trace_modified
+            } else {
+                false
+            }
+        }
+    } else {
+        false
+    };
+
+    if use_existing {
+        println!("Using existing traces and labels from {}", npz_path.display());
+        let mut npz_reader = NpzReader::new(File::open(&npz_path).expect("Failed to open npz file"))
+            .expect("Failed to read npz file");
+        let labels_array: Array1<u16> = npz_reader
+            .by_name("labels")
+            .expect("Failed to find 'labels' in NPZ file");
+
+        let traces: Vec<Array1<f32>> = npz_reader
+            .names()
+            .expect("Failed to get names from NPZ file")
+            .iter()
+            .filter_map(|name| {
+                name.starts_with("trace_").then(|| {
+                    npz_reader
+                        .by_name(name.as_str())
+                        .expect(&format!("Failed to find '{}' in NPZ file", name))
+                })
+            })
+            .collect_vec();
+        let num_traces = traces.len();
+        let traces_array: Array2<f32> = Array2::from_shape_vec(
+            (num_traces, traces[0].len()),
+            traces.into_iter().flatten().collect(),
+        )
+        .expect("Failed to create traces array");
+        Some((traces_array, labels_array))
+    } else {
+        set_stage("loading waveform");
+        println!("Loading signals from the waveform...");
+        let start_time = std::time::Instant::now();
+        let (signals, time_table) =
+            match load_waveform(&trace_file_path, !single_thread, show_progress, progress, stop) {
+                Ok(loaded) => loaded,
+                Err(scasim::Error::Cancelled) => return None,
+                Err(e) => panic!("Failed to load waveform: {e}"),
+            };
+        println!(
+            "It took {:.2}s to load {} signals with {} time points",
+            start_time.elapsed().as_secs_f32(),
+            signals.len(),
+            time_table.len()
+        );
+
+        set_stage("generating power trace");
+        println!("Generating power trace...");
+        let start_time = std::time::Instant::now();
+        let (time_table, power_table) = match generate_power_trace(
+            &signals,
+            &time_table,
+            |(t, _)| *t % cp == 0,
+            clock_period.is_some(),
+            model,
+            progress,
+            stop,
+        ) {
+            Ok(trace) => trace,
+            Err(scasim::Error::Cancelled) => return None,
+            Err(e) => panic!("Failed to convert waveform to power trace: {e}"),
+        };
+        println!(
+            "It took {:.2}s to generate the power trace",
+            start_time.elapsed().as_secs_f32()
+        );
+
+        set_stage("cutting traces");
+        println!("Cutting traces based on markers...");
+        let start_time = std::time::Instant::now();
+        let (traces_array, labels_array) = cut_trace(&power_table, &time_table, &meta_markers);
+
+        let (num_traces, cur_samples_per_trace) = traces_array.dim();
+        println!(
+            "Cut traces in {:.2}s, resulting in {} traces with a maximum of {} samples each",
+            start_time.elapsed().as_secs_f32(),
+            num_traces,
+            cur_samples_per_trace
+        );
+        println!("Saving traces and labels to trace store...");
+        let start_time: std::time::Instant = std::time::Instant::now();
+
+        scasim::write_trace_store(
+            &store_path,
+            traces_array.view(),
+            labels_array.view(),
+            scasim::trace_store::Meta {
+                samples_per_trace: cur_samples_per_trace,
+                trace_count: num_traces,
+                model,
+            },
+            compression,
+            compression_level,
+        )
+        .expect("Failed to write trace store");
+        // Record the manifest so future runs can validate this cache by content.
+        std::fs::write(
+            &manifest_path,
+            serde_json::to_string_pretty(&manifest).expect("Failed to serialize manifest"),
+        )
+        .expect("Failed to write cache manifest");
+        println!(
+            "Saved traces and labels to {} in {:.2}s\n",
+            store_path.display(),
+            start_time.elapsed().as_secs_f32()
+        );
+
+        Some((traces_array, labels_array))
+    }
+}
+
 fn main() -> miette::Result<()> {
     let args = Args::parse();
 
@@ -161,16 +544,16 @@ fn main() -> miette::Result<()> {
     };
     let order = args.order;
 
+    let detector_kind: scasim::DetectorKind = args.detector.into();
+
     let mut samples_per_trace = 0;
-    let mut max_t_values = vec![Vec::<f64>::new(); order];
-    let mut num_traces_so_far = vec![];
-    // Initial max |t| is 0.0 for each order corresponding to 0 traces
-    max_t_values.iter_mut().for_each(|v| {
-        v.push(0.0);
-    });
-    num_traces_so_far.push(0);
+    // One max-score series per detector output row (t-test orders, or the single
+    // CPA correlation row). Sized lazily from the first `finalize`, seeded with a
+    // 0.0 checkpoint corresponding to 0 traces.
+    let mut max_t_values: Vec<Vec<f64>> = Vec::new();
+    let mut num_traces_so_far = vec![0];
 
-    let mut maybe_ttacc: Option<ttest::Ttest> = None;
+    let mut maybe_detector: Option<Box<dyn LeakageDetector>> = None;
 
     if filenames.is_empty() {
         panic!("No meta files provided. Please specify at least one NPZ file.");
@@ -193,184 +576,168 @@ fn main() -> miette::Result<()> {
     );
 
 
-    let collected_traces = filenames.into_par_iter().filter_map(|metadata_path| {
-        if !metadata_path.exists() {
-            log::error!(
-                "Metadata file '{}' does not exist!",
-                metadata_path.display()
-            );
-            return None;
+    // Bounded-memory producer/consumer pipeline: worker threads load, generate
+    // and cut each file's traces and push batches into a bounded channel; the
+    // single consumer below folds them into the t-test accumulator one batch at
+    // a time, so peak memory is the consumer's current batch, at most
+    // `max_in_flight` queued batches and one file per worker, plus the
+    // accumulator state, rather than every file's traces at once.
+    let num_workers = default_num_threads.max(1);
+    let max_in_flight = args.max_in_flight.unwrap_or(num_workers).max(1);
+    let max_samples_per_batch = args.max_samples_per_batch;
+    let single_thread = args.single_thread;
+    let show_progress = args.show_progress;
+    let use_existing = args.use_existing;
+    let compression: scasim::Compression = args.compression.into();
+    let compression_level = args.compression_level;
+
+    // Unified progress channel: every phase (header/body/signal load, trace
+    // generation and the t-test fold) reports through this single sender so a
+    // front-end renders one view. Created up front so the loader workers can
+    // forward their load/generate progress, not just the final fold.
+    let (progress_tx, progress_rx) = crossbeam_channel::unbounded::<ProgressData>();
+    let progress_sender: ProgressSender = args.show_progress.then_some(progress_tx);
+
+    // Single stop source: a Ctrl-C handler sets a shared flag that every phase
+    // polls, bailing out with `Error::Cancelled` instead of leaving a
+    // half-finished run. A flag rather than a channel so one raise is seen by
+    // all N workers and the consumer — a channel message is consumed by a single
+    // receiver and lost to the rest.
+    let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    {
+        let stop = stop.clone();
+        if let Err(e) = ctrlc::set_handler(move || {
+            stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        }) {
+            warn!("failed to install Ctrl-C handler: {e}");
         }
+    }
 
-        let metadata_json = get_metadata(
-            &metadata_path,
-            metadata_path.extension().map_or(false, |ext| ext == "gz"),
-        )
-        .expect("Failed to load metadata!");
-
-        let parent_folder_path = metadata_path
-            .parent()
-            .expect("Failed to get parent folder of metadata file")
-            .to_path_buf();
-
-        let trace_filename = metadata_json
-            .get("trace_filename")
-            .and_then(|v| v.as_str())
-            .expect("trace_filename not found in metadata");
-
-        let trace_file_path = parent_folder_path.join(trace_filename);
-
-        let npz_path = parent_folder_path.join(npz_filename);
-
-        let use_existing = if args.use_existing && npz_path.exists() {
-            if !trace_file_path.exists() {
-                true
-            } else {
-                // Check if the npz file is older than the trace file
-                let npz_modified = std::fs::metadata(&npz_path).and_then(|m| m.modified());
-                let trace_modified = std::fs::metadata(&trace_file_path).and_then(|m| m.modified());
-                if let (Ok(npz_modified), Ok(trace_modified)) = (npz_modified, trace_modified) {
-                    // Use existing if npz file is newer than trace file
-                    npz_modified > trace_modified
-                } else {
-                    false
-                }
-            }
-        } else {
-            false
-        };
-
-        if use_existing {
-            println!(
-                "Using existing traces and labels from {}",
-                npz_path.display()
-            );
-            let mut npz_reader =
-                NpzReader::new(File::open(&npz_path).expect("Failed to open npz file"))
-                    .expect("Failed to read npz file");
-            let labels_array: Array1<u16> = npz_reader
-                .by_name("labels")
-                .expect("Failed to find 'labels' in NPZ file");
-
-            let traces: Vec<Array1<f32>> = npz_reader
-                .names()
-                .expect("Failed to get names from NPZ file")
-                .iter()
-                .filter_map(|name| {
-                    name.starts_with("trace_").then(|| {
-                        npz_reader
-                            .by_name(name.as_str())
-                            .expect(&format!("Failed to find '{}' in NPZ file", name))
-                    })
-                })
-                .collect_vec();
-            let num_traces = traces.len();
-            let traces_array: Array2<f32> = Array2::from_shape_vec(
-                (num_traces, traces[0].len()),
-                traces.into_iter().flatten().collect(),
-            )
-            .expect("Failed to create traces array");
-            Some((traces_array, labels_array))
-        } else {
-            let clock_period = metadata_json.get("clock_period").and_then(|v| v.as_u64());
-            let cp = clock_period.unwrap_or_default();
-            // .expect("clock_period not found in the metadata"); // FIXME optional
-            let meta_markers = metadata_json
-                .get("markers")
-                .map(|v| {
-                    v.as_array()
-                        .unwrap()
-                        .into_iter()
-                        .map(|e| {
-                            let (start_time, end_time, label) =  e.as_array()
-                                .unwrap()
-                                .into_iter()
-                                .map(|i| i.as_u64().unwrap())
-                                .collect_tuple().unwrap();
-                            (start_time, end_time, label as u16)
-                        })
-                        .collect_vec()
-                })
-                .expect("markers not found in metadata");
-
-            if false {
-                let (traces_array, labels_array, _) = traces_from_fst(
-                    &trace_file_path,
-                    &meta_markers,
-                    |t| clock_period.map(|cp| t % cp == 0).unwrap_or(true),
-                ).expect("Failed to load traces from FST file");
-                Some((traces_array, labels_array))
-            } else {
-            println!("Loading signals from the waveform...");
-            let start_time = std::time::Instant::now();
-            let (signals, time_table) =
-                load_waveform(&trace_file_path, !args.single_thread, args.show_progress)
-                    .expect("Failed to load waveform!");
-            println!(
-                "It took {:.2}s to load {} signals with {} time points",
-                start_time.elapsed().as_secs_f32(),
-                signals.len(),
-                time_table.len()
-            );
-
-            println!("Generating power trace...");
-            let start_time = std::time::Instant::now();
-            let (time_table, power_table) = generate_power_trace(
-                &signals,
-                &time_table,
-                |(t, _)| *t % cp == 0,
-                clock_period.is_some(),
+    let (work_tx, work_rx) = crossbeam_channel::unbounded::<PathBuf>();
+    for f in filenames {
+        work_tx.send(f).expect("Failed to enqueue work item");
+    }
+    drop(work_tx);
+
+    let (batch_tx, batch_rx) =
+        crossbeam_channel::bounded::<(Array2<f32>, Array1<u16>)>(max_in_flight);
+
+    // Unified progress: a MultiProgress with one per-worker bar showing the file
+    // and stage it is on, plus a global bar tracking total traces folded into the
+    // accumulator. Disabled when progress is off or stdout is not a TTY, in which
+    // case the per-stage `println!`/`info!` logging stands in.
+    let use_bars = show_progress && std::io::stdout().is_terminal();
+    let multi = use_bars.then(indicatif::MultiProgress::new);
+    let worker_bars: Vec<Option<indicatif::ProgressBar>> = (0..num_workers)
+        .map(|_| {
+            multi.as_ref().map(|m| {
+                let bar = m.add(indicatif::ProgressBar::new_spinner());
+                bar.set_style(
+                    indicatif::ProgressStyle::with_template("  {spinner} {msg}").unwrap(),
+                );
+                bar.enable_steady_tick(std::time::Duration::from_millis(120));
+                bar.set_message("idle");
+                bar
+            })
+        })
+        .collect();
+    let global_bar = multi.as_ref().map(|m| {
+        let bar = m.add(indicatif::ProgressBar::new_spinner());
+        bar.set_style(
+            indicatif::ProgressStyle::with_template(
+                "[{elapsed_precise}] {spinner} {pos} traces folded ({per_sec}, ETA {eta})",
             )
-            .expect("Failed to convert waveform to power trace!");
-            println!(
-                "It took {:.2}s to generate the power trace",
-                start_time.elapsed().as_secs_f32()
-            );
+            .unwrap(),
+        );
+        bar
+    });
 
-            
+    let workers: Vec<_> = (0..num_workers)
+        .map(|worker_id| {
+            let work_rx = work_rx.clone();
+            let batch_tx = batch_tx.clone();
+            let stage_bar = worker_bars[worker_id].clone();
+            let progress = progress_sender.clone();
+            let stop = Some(stop.clone());
+            std::thread::spawn(move || {
+                for metadata_path in work_rx.iter() {
+                    if scasim::progress::is_cancelled(&stop) {
+                        break;
+                    }
+                    let Some((traces, labels)) = process_metadata_file(
+                        &metadata_path,
+                        single_thread,
+                        show_progress,
+                        use_existing,
+                        npz_filename,
+                        compression,
+                        compression_level,
+                        stage_bar.as_ref(),
+                        &progress,
+                        &stop,
+                    ) else {
+                        continue;
+                    };
+                    // Optionally split a large per-file trace set so no single
+                    // batch exceeds the requested trace count.
+                    match max_samples_per_batch {
+                        Some(n) if n > 0 && traces.nrows() > n => {
+                            let mut start = 0;
+                            while start < traces.nrows() {
+                                let end = (start + n).min(traces.nrows());
+                                let sub_traces = traces.slice(s![start..end, ..]).to_owned();
+                                let sub_labels = labels.slice(s![start..end]).to_owned();
+                                if batch_tx.send((sub_traces, sub_labels)).is_err() {
+                                    return;
+                                }
+                                start = end;
+                            }
+                        }
+                        _ => {
+                            let _ = batch_tx.send((traces, labels));
+                        }
+                    }
+                }
+                if let Some(bar) = &stage_bar {
+                    bar.set_message("done");
+                    bar.finish();
+                }
+            })
+        })
+        .collect();
+    // Drop our own sender so the consumer's channel closes once all workers exit.
+    drop(batch_tx);
 
-            println!("Cutting traces based on markers...");
-            let start_time = std::time::Instant::now();
-            let (traces_array, labels_array) = cut_trace(&power_table, &time_table, &meta_markers);
 
-            let (num_traces, cur_samples_per_trace) = traces_array.dim();
-            println!(
-                "Cut traces in {:.2}s, resulting in {} traces with a maximum of {} samples each",
-                start_time.elapsed().as_secs_f32(),
-                num_traces,
-                cur_samples_per_trace
-            );
-            println!("Saving traces and labels to NPZ file...");
-            let start_time: std::time::Instant = std::time::Instant::now();
+    let mut total_collected_traces: usize = 0;
 
-            let mut npz = NpzWriter::new_compressed(
-                File::create(&npz_path).expect("Failed to create npz file"),
-            );
-            for (tidx, trace) in traces_array.outer_iter().enumerate() {
-                npz.add_array(format!("trace_{tidx}"), &trace)
-                    .expect("Failed to add array 'a' to npz");
+    // Drive the global progress bar off the shared progress channel, tracking the
+    // cumulative trace count folded into the accumulator.
+    let bar_handle = global_bar.map(|bar| {
+        std::thread::spawn(move || {
+            for p in progress_rx.iter() {
+                if p.phase == Phase::TTest {
+                    bar.set_position(p.done);
+                }
             }
-            npz.add_array("labels", &labels_array)
-                .expect("Failed to add array 'labels' to npz");
-            npz.finish().expect("Failed to finish writing npz file");
-            println!(
-                "Saved traces and labels to {} in {:.2}s\n",
-                npz_path.display(),
-                start_time.elapsed().as_secs_f32()
-            );
+            bar.finish();
+        })
+    });
 
-            Some((traces_array, labels_array))
-        }
+    // Consume batches as the workers produce them, folding each into the
+    // accumulator and dropping it before the next arrives. Trace accounting is
+    // cumulative and therefore independent of the order batches arrive in. The
+    // loop polls the stop receiver between batches and bails out cleanly on
+    // Ctrl-C, keeping whatever has been folded so far.
+    let mut t_values: Option<Array2<f64>> = None;
+    let mut cancelled = false;
+    for (traces_array, labels_array) in batch_rx.iter() {
+        if scasim::progress::is_cancelled(&Some(stop.clone())) {
+            warn!("cancelled: stopping the t-test fold after {total_collected_traces} traces");
+            cancelled = true;
+            break;
         }
-    }).collect_vec_list();
-
-
-    let mut total_collected_traces: usize = 0;
-    // must be done sequentially
-    let t_values = collected_traces
-        .into_iter()
-        .flatten()
-        .fold(None, |_prev_tvalues, (traces_array, labels_array)| {
+        t_values = {
             let (num_traces, cur_samples_per_trace) = traces_array.dim();
             total_collected_traces += num_traces;
             let traces_array = if samples_per_trace == 0 {
@@ -418,15 +785,37 @@ fn main() -> miette::Result<()> {
                 "Number of trace labels does not match number of traces"
             );
 
-            if maybe_ttacc.is_none() {
-                maybe_ttacc = Some(ttest::Ttest::new(samples_per_trace, order));
-            }
+            let detector = maybe_detector
+                .get_or_insert_with(|| detector_kind.build(samples_per_trace, order));
+
+            {
+                // Fold each trace through the selected detector. The scalar is a
+                // class label for the t-test and a hypothetical-leakage value for
+                // CPA; both ride the same `labels` column.
+                let mut sample_buf = vec![0f64; samples_per_trace];
+                for (row, &label) in traces_array.outer_iter().zip(labels_array.iter()) {
+                    for (dst, &v) in sample_buf.iter_mut().zip(row.iter()) {
+                        *dst = v as f64;
+                    }
+                    detector.update(&sample_buf, label as f64);
+                }
 
-            if let Some(ref mut ttacc) = maybe_ttacc {
-                // Update the ttest accumulator with the current traces and labels
-                ttacc.update(traces_array.view(), labels_array.view());
+                // Report the cumulative trace count so the global bar tracks
+                // total traces folded into the accumulator, not batch count.
+                scasim::progress::report(
+                    &progress_sender,
+                    Phase::TTest,
+                    total_collected_traces as u64,
+                    0,
+                    "",
+                );
 
-                let t_values = ttacc.get_ttest();
+                let t_values = detector.finalize();
+                // Size the per-row max-score series once the detector's output
+                // shape is known, seeding the 0-trace checkpoint.
+                if max_t_values.is_empty() {
+                    max_t_values = vec![vec![0.0]; t_values.nrows()];
+                }
                 max_t_values
                     .iter_mut()
                     .zip(t_values.rows())
@@ -440,11 +829,35 @@ fn main() -> miette::Result<()> {
                         );
                     });
                 Some(t_values)
-            } else {
-                panic!("Ttest accumulator is not initialized");
             }
-        })
-        .expect("Failed to compute t-test values");
+        };
+    }
+
+    // If we bailed out early, workers may still be parked in the bounded
+    // `batch_tx.send(...)`. Drop the receiver so those sends fail fast and the
+    // workers exit, instead of deadlocking the `worker.join()` below. In the
+    // normal path the channel is already drained, so this is a no-op.
+    drop(batch_rx);
+
+    // Drop the sender so the progress consumer thread can finish.
+    drop(progress_sender);
+    if let Some(handle) = bar_handle {
+        handle.join().unwrap();
+    }
+    for worker in workers {
+        worker.join().expect("A loader worker panicked");
+    }
+
+    let t_values = match t_values {
+        Some(t) => t,
+        None => {
+            if cancelled {
+                warn!("cancelled before any traces were folded; no results to write");
+                return Ok(());
+            }
+            panic!("Failed to compute t-test values");
+        }
+    };
 
     log::info!(
         "Total number of traces: {}",
@@ -466,13 +879,30 @@ fn main() -> miette::Result<()> {
     npz.finish().expect("Failed to finish writing npz file");
     info!("Saved t_values to {}", npz_path.display());
 
-    if args.plot {
-        let mut image_exporter = plotly_static::StaticExporterBuilder::default()
-            .pdf_export_timeout(1000)
-            // .offline_mode(true)
-            .build()
-            .expect("Failed to create static exporter");
+    // Export to standard interchange formats so the arrays can be fed to
+    // external tooling instead of only the plotly artifacts. One `.trs` trace
+    // per detector output row (t-test order or the CPA correlation row), plus
+    // `.npy` copies of both result matrices.
+    if args.export {
+        let npy_path = output_dir.join("t_values.npy");
+        info!("Exporting t_values to {}", npy_path.display());
+        write_npy(&npy_path, &t_values).expect("Failed to export t_values to .npy");
+
+        let trs_path = output_dir.join("t_values.trs");
+        info!("Exporting t_values to {}", trs_path.display());
+        let t_values_f32 = t_values.mapv(|v| v as f32);
+        write_trs(&trs_path, t_values_f32.view(), None).expect("Failed to export t_values to .trs");
+
+        let num_checkpoints = num_traces_so_far.len();
+        let max_t_flat: Vec<f64> = max_t_values.iter().flatten().copied().collect();
+        let max_t_array = Array2::from_shape_vec((max_t_values.len(), num_checkpoints), max_t_flat)
+            .expect("Failed to shape max_t_values for export");
+        let max_npy_path = output_dir.join("max_t_values.npy");
+        info!("Exporting max_t_values to {}", max_npy_path.display());
+        write_npy(&max_npy_path, &max_t_array).expect("Failed to export max_t_values to .npy");
+    }
 
+    if args.plot {
         let plots_config = plotly::Configuration::new()
             .display_mode_bar(plotly::configuration::DisplayModeBar::Hover)
             .show_link(false)
@@ -481,6 +911,8 @@ fn main() -> miette::Result<()> {
             .responsive(true)
             .typeset_math(true);
 
+        let mut backend = scasim::RenderBackendKind::from(args.render_backend).build(&plots_config)?;
+
         let t_threshold = Some(4.5);
 
         plot_t_traces(
@@ -490,7 +922,7 @@ fn main() -> miette::Result<()> {
             &output_dir,
             args.show_plots,
             &plots_config,
-            &mut image_exporter,
+            backend.as_mut(),
         )?;
 
         plot_max_t_values(
@@ -500,7 +932,7 @@ fn main() -> miette::Result<()> {
             &output_dir,
             args.show_plots,
             &plots_config,
-            &mut image_exporter,
+            backend.as_mut(),
         )?;
     }
 
@@ -0,0 +1,141 @@
+//! Pluggable streaming leakage detectors.
+//!
+//! The analysis pipeline historically hard-coded the t-test. This module lifts
+//! it behind a [`LeakageDetector`] trait so different detection methods share one
+//! streaming interface — fold traces one at a time, then emit a per-sample score
+//! matrix the plotting layer renders directly. Detectors are `Send + Sync` so a
+//! pool of them can run in parallel, in the spirit of the parallel rule runner.
+//!
+//! Two detectors ship here: [`TTestDetector`], wrapping the higher-order TVLA
+//! accumulator, and [`CpaDetector`], a correlation power analysis that produces
+//! Pearson `ρ(t)` from incremental sums without storing every trace. A caller
+//! selects between them at runtime through the [`DetectorKind`] config enum.
+
+use crate::tvla::HigherOrderTtest;
+use ndarray::{Array1, Array2};
+
+/// A streaming per-sample leakage detector.
+///
+/// `update` folds a single trace together with an attacker-supplied scalar —
+/// interpreted as a class label (`0`/`1`) by the t-test and as a hypothetical
+/// leakage value by CPA — and `finalize` returns a `rows × samples` score matrix
+/// laid out exactly like the first-order `t_values` array, so `plot_t_traces`
+/// can render it unchanged.
+pub trait LeakageDetector: Send + Sync {
+    /// Fold one `trace` with its associated class or hypothetical-leakage value.
+    fn update(&mut self, trace: &[f64], class_or_intermediate: f64);
+    /// Per-sample score matrix, ready for the plotting layer.
+    fn finalize(&self) -> Array2<f64>;
+}
+
+/// Selects which [`LeakageDetector`] to build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectorKind {
+    /// Higher-order Welch t-test (fixed-vs-random TVLA).
+    TTest,
+    /// Correlation power analysis against a hypothetical-leakage model.
+    Cpa,
+}
+
+impl DetectorKind {
+    /// Build a boxed detector for `samples`-long traces. `order` is only used by
+    /// the t-test (it is the highest statistical order); CPA ignores it.
+    pub fn build(self, samples: usize, order: usize) -> Box<dyn LeakageDetector> {
+        match self {
+            DetectorKind::TTest => Box::new(TTestDetector::new(samples, order)),
+            DetectorKind::Cpa => Box::new(CpaDetector::new(samples)),
+        }
+    }
+}
+
+/// Adapts the batched [`HigherOrderTtest`] accumulator to the per-trace
+/// [`LeakageDetector`] interface.
+pub struct TTestDetector {
+    inner: HigherOrderTtest,
+    samples: usize,
+}
+
+impl TTestDetector {
+    pub fn new(samples: usize, order: usize) -> Self {
+        Self {
+            inner: HigherOrderTtest::new(samples, order),
+            samples,
+        }
+    }
+}
+
+impl LeakageDetector for TTestDetector {
+    fn update(&mut self, trace: &[f64], class_or_intermediate: f64) {
+        assert_eq!(trace.len(), self.samples, "trace length does not match");
+        let row = Array2::from_shape_fn((1, self.samples), |(_, s)| trace[s] as f32);
+        let label = Array1::from_elem(1, class_or_intermediate as u16);
+        self.inner.update(row.view(), label.view());
+    }
+
+    fn finalize(&self) -> Array2<f64> {
+        self.inner.get_ttest()
+    }
+}
+
+/// Correlation power analysis over a single hypothetical-leakage model.
+///
+/// Keeps the incremental sums `Σh`, `Σh²`, `Σx_t`, `Σx_t²` and `Σh·x_t` so the
+/// Pearson correlation `ρ(t)` between the model vector and the per-sample trace
+/// values can be produced at [`finalize`](LeakageDetector::finalize) without ever
+/// storing all traces.
+pub struct CpaDetector {
+    samples: usize,
+    n: u64,
+    sum_h: f64,
+    sum_h2: f64,
+    sum_x: Array1<f64>,
+    sum_x2: Array1<f64>,
+    sum_hx: Array1<f64>,
+}
+
+impl CpaDetector {
+    pub fn new(samples: usize) -> Self {
+        Self {
+            samples,
+            n: 0,
+            sum_h: 0.0,
+            sum_h2: 0.0,
+            sum_x: Array1::zeros(samples),
+            sum_x2: Array1::zeros(samples),
+            sum_hx: Array1::zeros(samples),
+        }
+    }
+}
+
+impl LeakageDetector for CpaDetector {
+    fn update(&mut self, trace: &[f64], class_or_intermediate: f64) {
+        assert_eq!(trace.len(), self.samples, "trace length does not match");
+        let h = class_or_intermediate;
+        self.sum_h += h;
+        self.sum_h2 += h * h;
+        for (s, &x) in trace.iter().enumerate() {
+            self.sum_x[s] += x;
+            self.sum_x2[s] += x * x;
+            self.sum_hx[s] += h * x;
+        }
+        self.n += 1;
+    }
+
+    /// Pearson correlation of the model against the measured power at every
+    /// sample, as a single-row score matrix. Samples with zero variance yield
+    /// `NaN` so the plotting layer skips them.
+    fn finalize(&self) -> Array2<f64> {
+        let n = self.n as f64;
+        let h_var = n * self.sum_h2 - self.sum_h * self.sum_h;
+        Array2::from_shape_fn((1, self.samples), |(_, s)| {
+            let sum_x = self.sum_x[s];
+            let x_var = n * self.sum_x2[s] - sum_x * sum_x;
+            let denom = h_var * x_var;
+            if denom <= 0.0 {
+                f64::NAN
+            } else {
+                (n * self.sum_hx[s] - self.sum_h * sum_x) / denom.sqrt()
+            }
+        })
+    }
+}
@@ -0,0 +1,223 @@
+//! Exporters to standard side-channel interchange formats.
+//!
+//! The plotting pipeline keeps its results in memory as `ndarray` matrices and
+//! `Vec`s and, until now, only serialized them as `plotly` HTML/JSON. That locks
+//! the data inside this crate. This module is a small format writer — in the
+//! spirit of the wycheproof-to-raw-blob converter — that turns those in-memory
+//! vectors into portable on-disk representations external analysis tools can
+//! read: NumPy [`write_npy`] `.npy` arrays and Riscure-style [`write_trs`] trace
+//! sets.
+
+use ndarray::{ArrayBase, ArrayView2, Data, Dimension};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// A scalar type that can be serialized into a NumPy `.npy` array: it knows its
+/// NumPy dtype string (little-endian) and how to emit its raw bytes.
+pub trait NpyElement: Copy {
+    /// NumPy `descr` string, e.g. `<f4` for little-endian `f32`.
+    const DESCR: &'static str;
+    fn write_le<W: Write>(self, writer: &mut W) -> io::Result<()>;
+}
+
+macro_rules! impl_npy_element {
+    ($ty:ty, $descr:literal) => {
+        impl NpyElement for $ty {
+            const DESCR: &'static str = $descr;
+            #[inline]
+            fn write_le<W: Write>(self, writer: &mut W) -> io::Result<()> {
+                writer.write_all(&self.to_le_bytes())
+            }
+        }
+    };
+}
+
+impl_npy_element!(f32, "<f4");
+impl_npy_element!(f64, "<f8");
+impl_npy_element!(u8, "|u1");
+impl_npy_element!(u16, "<u2");
+impl_npy_element!(u64, "<u8");
+
+/// Build the `.npy` header dict and pad the whole header block (magic, version,
+/// length field and dict) to a 64-byte boundary, as the format requires.
+fn npy_header(descr: &str, shape: &[usize]) -> Vec<u8> {
+    let shape_str = match shape {
+        // A 1-D shape is written `(N,)`; higher ranks comma-separate without the
+        // trailing comma.
+        [n] => format!("({n},)"),
+        _ => format!("({})", shape.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    let dict = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape_str}, }}");
+
+    // magic (6) + version (2) + header-length field (2) = 10 bytes precede the
+    // dict; pad the dict with spaces so the total is a multiple of 64 and ends
+    // with a newline.
+    const PREAMBLE: usize = 10;
+    let unpadded = PREAMBLE + dict.len() + 1;
+    let padded = unpadded.div_ceil(64) * 64;
+    let pad = padded - unpadded;
+    let header_len = (padded - PREAMBLE) as u16;
+
+    let mut buf = Vec::with_capacity(padded);
+    buf.extend_from_slice(b"\x93NUMPY");
+    buf.extend_from_slice(&[1, 0]); // version 1.0
+    buf.extend_from_slice(&header_len.to_le_bytes());
+    buf.extend_from_slice(dict.as_bytes());
+    buf.extend(std::iter::repeat(b' ').take(pad));
+    buf.push(b'\n');
+    buf
+}
+
+/// Write an `ndarray` array to `path` as a NumPy `.npy` file (version 1.0, C
+/// order), readable by `numpy.load`.
+pub fn write_npy<A, S, D, P>(path: P, array: &ArrayBase<S, D>) -> io::Result<()>
+where
+    A: NpyElement,
+    S: Data<Elem = A>,
+    D: Dimension,
+    P: AsRef<Path>,
+{
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(&npy_header(A::DESCR, array.shape()))?;
+    // `.npy` is C-contiguous for `fortran_order: False`; iterating the standard
+    // layout visits elements in that order regardless of the input's strides.
+    for &v in array.as_standard_layout().iter() {
+        v.write_le(&mut file)?;
+    }
+    file.flush()
+}
+
+/// Riscure `.trs` sample coding for little-endian `f32` samples: the low nibble
+/// is the sample length in bytes, the `0x10` bit marks floating-point.
+const SC_FLOAT_4: u8 = 0x14;
+
+/// Emit one `.trs` header tag. Lengths below `0x80` are a single byte; larger
+/// lengths are `0x80 | byte_count` followed by the little-endian length.
+fn write_tag<W: Write>(writer: &mut W, tag: u8, value: &[u8]) -> io::Result<()> {
+    writer.write_all(&[tag])?;
+    let len = value.len();
+    if len < 0x80 {
+        writer.write_all(&[len as u8])?;
+    } else {
+        let bytes = (len as u32).to_le_bytes();
+        let used = 4 - ((len as u32).leading_zeros() as usize / 8);
+        writer.write_all(&[0x80 | used as u8])?;
+        writer.write_all(&bytes[..used])?;
+    }
+    writer.write_all(value)
+}
+
+/// Write `traces` to `path` as a Riscure-style `.trs` trace set: a tagged header
+/// block (number of traces, samples per trace, sample coding and optional
+/// per-trace data length) terminated by a trace-block marker, followed by each
+/// trace's optional `data` bytes and its samples packed little-endian.
+pub fn write_trs<P: AsRef<Path>>(
+    path: P,
+    traces: ArrayView2<f32>,
+    data: Option<ArrayView2<u8>>,
+) -> io::Result<()> {
+    let (num_traces, samples_per_trace) = traces.dim();
+    let data_len = match data {
+        Some(d) => {
+            assert_eq!(
+                d.nrows(),
+                num_traces,
+                "number of data rows does not match number of traces"
+            );
+            d.ncols()
+        }
+        None => 0,
+    };
+
+    let mut file = BufWriter::new(File::create(path)?);
+    // 0x41 NT: number of traces, 0x42 NS: samples per trace, 0x43 SC: sample
+    // coding, 0x44 DS: per-trace data length (only when present), 0x5f TB: end
+    // of header / start of trace block.
+    write_tag(&mut file, 0x41, &(num_traces as u32).to_le_bytes())?;
+    write_tag(&mut file, 0x42, &(samples_per_trace as u32).to_le_bytes())?;
+    write_tag(&mut file, 0x43, &[SC_FLOAT_4])?;
+    if data_len > 0 {
+        write_tag(&mut file, 0x44, &(data_len as u16).to_le_bytes())?;
+    }
+    write_tag(&mut file, 0x5f, &[])?;
+
+    for (i, trace) in traces.outer_iter().enumerate() {
+        if let Some(d) = data {
+            file.write_all(d.row(i).as_slice().expect("data row is not contiguous"))?;
+        }
+        for &v in trace.iter() {
+            file.write_all(&v.to_le_bytes())?;
+        }
+    }
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    fn scratch(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("scasim-export-{}-{name}", std::process::id()))
+    }
+
+    /// The `.npy` header must satisfy the invariants `numpy.load` checks: the
+    /// magic string, a version, a header-length field whose block is a multiple
+    /// of 64 bytes and ends in a newline, and a parseable dict with the right
+    /// dtype and shape. The data payload follows in C order.
+    #[test]
+    fn npy_header_is_numpy_loadable() {
+        let arr = array![[1.0f64, 2.0, 3.0], [4.0, 5.0, 6.0]];
+        let path = scratch("t_values.npy");
+        write_npy(&path, &arr).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(&bytes[0..6], b"\x93NUMPY");
+        assert_eq!(&bytes[6..8], &[1, 0], "version 1.0");
+        let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        let total_header = 10 + header_len;
+        assert_eq!(total_header % 64, 0, "header block must be 64-byte aligned");
+        assert_eq!(bytes[total_header - 1], b'\n', "header ends with newline");
+
+        let dict = std::str::from_utf8(&bytes[10..total_header]).unwrap();
+        assert!(dict.contains("'descr': '<f8'"), "dict was {dict:?}");
+        assert!(dict.contains("'fortran_order': False"));
+        assert!(dict.contains("'shape': (2, 3)"), "dict was {dict:?}");
+
+        // Payload is the six values in C order, little-endian f64.
+        let payload = &bytes[total_header..];
+        assert_eq!(payload.len(), 6 * 8);
+        let first = f64::from_le_bytes(payload[0..8].try_into().unwrap());
+        let last = f64::from_le_bytes(payload[40..48].try_into().unwrap());
+        assert_eq!(first, 1.0);
+        assert_eq!(last, 6.0);
+    }
+
+    /// The `.trs` header tags carry the trace geometry and sample coding, the
+    /// trace block starts right after the `0x5f` marker, and each trace's samples
+    /// are packed little-endian.
+    #[test]
+    fn trs_header_and_samples() {
+        let traces = array![[0.5f32, 1.5], [2.5, 3.5]];
+        let path = scratch("t_values.trs");
+        write_trs(&path, traces.view(), None).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        // NT(0x41)=2, NS(0x42)=2, SC(0x43)=0x14, TB(0x5f) terminates the header.
+        assert_eq!(bytes[0], 0x41);
+        assert_eq!(bytes[1], 4);
+        assert_eq!(u32::from_le_bytes(bytes[2..6].try_into().unwrap()), 2);
+        assert_eq!(bytes[6], 0x42);
+        assert_eq!(u32::from_le_bytes(bytes[8..12].try_into().unwrap()), 2);
+        assert_eq!(&bytes[12..15], &[0x43, 1, SC_FLOAT_4]);
+        assert_eq!(&bytes[15..17], &[0x5f, 0]);
+
+        let payload = &bytes[17..];
+        assert_eq!(payload.len(), 4 * 4);
+        assert_eq!(f32::from_le_bytes(payload[0..4].try_into().unwrap()), 0.5);
+        assert_eq!(f32::from_le_bytes(payload[12..16].try_into().unwrap()), 3.5);
+    }
+}
@@ -1,20 +1,143 @@
 use indicatif::ProgressStyle;
 use itertools::Itertools;
-use log::{debug, info};
+use log::{debug, info, warn};
+use ndarray::{Array1, Array2};
+use ndarray_npz::NpzReader;
 use num_format::{Locale, ToFormattedString};
-use std::path::Path;
+use std::fs::File;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::thread;
 
+pub mod detector;
+pub mod export;
 pub mod fst;
 pub mod optional_filter;
 pub mod plot;
 pub mod power_model;
+pub mod progress;
+pub mod render;
+pub mod trace_store;
+pub mod tvla;
 
+pub use detector::{CpaDetector, DetectorKind, LeakageDetector, TTestDetector};
+pub use export::{write_npy, write_trs};
 pub use fst::*;
 pub use optional_filter::*;
 pub use power_model::*;
+pub use tvla::HigherOrderTtest;
+pub use progress::{Error, Phase, ProgressData, ProgressSender, StopReceiver};
+pub use render::{ChartSpec, RenderBackend, RenderBackendKind, Series};
+pub use trace_store::{
+    Compression, DEFAULT_COMPRESSION_LEVEL, Meta, TraceStore, append_trace_store, open_trace_store,
+    write_trace_store,
+};
+
+/// Default number of traces read into memory at once when streaming an NPZ
+/// archive into the t-test accumulator.
+pub const DEFAULT_TTEST_CHUNK_SIZE: usize = 4096;
+
+/// Fold a set of NPZ trace archives into a [`HigherOrderTtest`] accumulator without
+/// ever materializing a whole file at once.
+///
+/// Each archive stores one `trace_i` array per trace plus a `labels` array. The
+/// traces are read lazily in chunks of `chunk_size` and fed to `update`
+/// incrementally, so peak memory is bounded by a single chunk rather than the
+/// size of the file. This matches the per-file incremental `update` design but
+/// pushes the chunking inside each file, letting archives far larger than RAM be
+/// processed.
+pub fn ttest_over_npz_chunked<P: AsRef<Path>>(
+    filenames: &[P],
+    order: usize,
+    chunk_size: usize,
+    progress: &ProgressSender,
+) -> Result<HigherOrderTtest, Error> {
+    assert!(chunk_size > 0, "chunk_size must be greater than 0");
+
+    let mut maybe_ttacc: Option<HigherOrderTtest> = None;
+    let mut samples_per_trace = 0usize;
+
+    for filename in filenames {
+        let display_name = filename.as_ref().display().to_string();
+        // Progress is reported per file: `done` counts this file's traces so it
+        // stays comparable to `total_traces` below.
+        let mut traces_done: u64 = 0;
+        let mut npz_reader = NpzReader::new(
+            File::open(&filename).unwrap_or_else(|e| panic!("Failed to open NPZ file: {e}")),
+        )
+        .expect("Failed to parse NPZ file");
+
+        // Collect the trace entry names ordered by their numeric suffix so the
+        // stream matches the on-disk trace order.
+        let mut trace_names: Vec<(usize, String)> = npz_reader
+            .names()
+            .expect("Failed to get names from NPZ file")
+            .into_iter()
+            .filter_map(|name| {
+                name.strip_prefix("trace_")
+                    .and_then(|idx| idx.parse::<usize>().ok())
+                    .map(|idx| (idx, name.clone()))
+            })
+            .collect();
+        trace_names.sort_unstable_by_key(|(idx, _)| *idx);
+
+        let labels: Array1<u16> = npz_reader
+            .by_name("labels")
+            .expect("Failed to find 'labels' in NPZ file");
+        let total_traces = trace_names.len() as u64;
+
+        for chunk in trace_names.chunks(chunk_size) {
+            // read only this chunk's traces into memory
+            let rows: Vec<Array1<f32>> = chunk
+                .iter()
+                .map(|(_, name)| {
+                    npz_reader
+                        .by_name(name.as_str())
+                        .unwrap_or_else(|_| panic!("Failed to find '{name}' in NPZ file"))
+                })
+                .collect();
+            if samples_per_trace == 0 {
+                samples_per_trace = rows[0].len();
+            }
+            let chunk_labels = Array1::from_iter(
+                chunk.iter().map(|(idx, _)| labels[*idx]),
+            );
+            // Traces from a later file/chunk may not share the first chunk's
+            // length. Rather than panic in `from_shape_vec`, reconcile each row
+            // to `samples_per_trace` the way the streaming fold does: truncate a
+            // longer trace and zero-pad a shorter one, logging the mismatch.
+            let mut chunk_array = Array2::<f32>::zeros((rows.len(), samples_per_trace));
+            for (row_idx, row) in rows.iter().enumerate() {
+                if row.len() != samples_per_trace {
+                    warn!(
+                        "{display_name}: trace with {} samples does not match the expected {}; {}",
+                        row.len(),
+                        samples_per_trace,
+                        if row.len() > samples_per_trace {
+                            "truncating"
+                        } else {
+                            "zero-padding"
+                        }
+                    );
+                }
+                let copy_len = row.len().min(samples_per_trace);
+                chunk_array
+                    .slice_mut(ndarray::s![row_idx, ..copy_len])
+                    .assign(&row.slice(ndarray::s![..copy_len]));
+            }
+
+            let ttacc = maybe_ttacc
+                .get_or_insert_with(|| HigherOrderTtest::new(samples_per_trace, order));
+            ttacc.update(chunk_array.view(), chunk_labels.view());
+
+            traces_done += chunk.len() as u64;
+            progress::report(progress, Phase::TTest, traces_done, total_traces, &display_name);
+        }
+    }
+
+    Ok(maybe_ttacc.expect("No traces found in the provided NPZ files"))
+}
 
 pub fn markers_to_time_indices(
     meta_markers: &[(u64, u64, u16)],
@@ -47,17 +170,29 @@ pub fn load_waveform<P: AsRef<Path>>(
     filename: P,
     multi_thread: bool,
     show_progress: bool,
-) -> Result<(Vec<(wellen::SignalRef, wellen::Signal)>, Vec<u64>), wellen::WellenError> {
+    progress: &ProgressSender,
+    stop: &StopReceiver,
+) -> Result<(Vec<(wellen::SignalRef, wellen::Signal)>, Vec<u64>), Error> {
+    let display_name = filename.as_ref().display().to_string();
     let load_opts = wellen::LoadOptions {
         multi_thread,
         remove_scopes_with_empty_name: false,
     };
+    if progress::is_cancelled(stop) {
+        return Err(Error::Cancelled);
+    }
     // load header
+    progress::report(progress, Phase::LoadHeader, 0, 0, &display_name);
     let header = wellen::viewers::read_header_from_file(&filename, &load_opts)
         .expect("Failed to load file!");
 
     let body_len = header.body_len;
-    let (body_progress, progress) = if !show_progress || body_len == 0 {
+    // When a front-end supplies a progress channel we forward body-load progress
+    // through it; otherwise we fall back to the self-contained `indicatif` bar so
+    // the simple CLI keeps working unchanged.
+    let forward_to_channel = progress.is_some();
+    let (body_progress, progress_thread) = if body_len == 0 || (!show_progress && !forward_to_channel)
+    {
         debug!("show_progress: {}, body_len: {}", show_progress, body_len);
         (None, None)
     } else {
@@ -65,30 +200,41 @@ pub fn load_waveform<P: AsRef<Path>>(
         let p_out = p.clone();
         let done = Arc::new(AtomicBool::new(false));
         let done_out = done.clone();
+        let channel = progress.clone();
+        let name = display_name.clone();
         let t = thread::spawn(move || {
-            let bar = indicatif::ProgressBar::new(body_len);
-            bar.set_style(
-                ProgressStyle::with_template(
-                    "[{elapsed_precise}] {bar:40.cyan/blue} {decimal_bytes} ({percent_precise}%)",
-                )
-                .unwrap(),
-            );
+            let bar = (!forward_to_channel).then(|| {
+                let bar = indicatif::ProgressBar::new(body_len);
+                bar.set_style(
+                    ProgressStyle::with_template(
+                        "[{elapsed_precise}] {bar:40.cyan/blue} {decimal_bytes} ({percent_precise}%)",
+                    )
+                    .unwrap(),
+                );
+                bar
+            });
             loop {
                 // always update
                 let new_value = p.load(Ordering::SeqCst);
-                bar.set_position(new_value);
+                if let Some(bar) = &bar {
+                    bar.set_position(new_value);
+                }
+                progress::report(&channel, Phase::LoadBody, new_value, body_len, &name);
                 thread::sleep(std::time::Duration::from_millis(500));
                 // see if we are done
                 let now_done = done.load(Ordering::SeqCst);
                 if now_done {
-                    if bar.position() != body_len {
-                        debug!(
-                            "Final progress value was: {}, expected {}",
-                            bar.position(),
-                            body_len
-                        );
+                    if let Some(bar) = &bar {
+                        if bar.position() != body_len {
+                            debug!(
+                                "Final progress value was: {}, expected {}",
+                                bar.position(),
+                                body_len
+                            );
+                        }
+                        bar.finish_and_clear();
                     }
-                    bar.finish_and_clear();
+                    progress::report(&channel, Phase::LoadBody, body_len, body_len, &name);
                     break;
                 }
             }
@@ -102,10 +248,13 @@ pub fn load_waveform<P: AsRef<Path>>(
     let start_time = std::time::Instant::now();
     let body = wellen::viewers::read_body(header.body, &hierarchy, body_progress)
         .expect("Failed to load the waveform body!");
-    if let Some((done, t)) = progress {
+    if let Some((done, t)) = progress_thread {
         done.store(true, Ordering::SeqCst);
         t.join().unwrap();
     }
+    if progress::is_cancelled(stop) {
+        return Err(Error::Cancelled);
+    }
     info!("Read body in {:.2}s", start_time.elapsed().as_secs_f32());
 
     info!(
@@ -125,7 +274,21 @@ pub fn load_waveform<P: AsRef<Path>>(
     );
     let start_time = std::time::Instant::now();
     // wave_source.print_statistics();
+    progress::report(
+        progress,
+        Phase::LoadSignals,
+        0,
+        signal_refs.len() as u64,
+        &display_name,
+    );
     let signals = wave_source.load_signals(&signal_refs, &hierarchy, load_opts.multi_thread);
+    progress::report(
+        progress,
+        Phase::LoadSignals,
+        signal_refs.len() as u64,
+        signal_refs.len() as u64,
+        &display_name,
+    );
     info!(
         "Loaded signals in {:.2}s",
         start_time.elapsed().as_secs_f32()
@@ -141,20 +304,32 @@ pub fn generate_power_trace<F: Fn(&(&u64, f32)) -> bool>(
     //    filter_predicate: Option<fn((u64, f32)) -> bool>,
     // filter_predicate: fn(&(&u64, f32)) -> bool,
     do_filter: bool,
-) -> Result<(Vec<u64>, Vec<f32>), wellen::WellenError> {
+    model: LeakageModel,
+    progress: &ProgressSender,
+    stop: &StopReceiver,
+) -> Result<(Vec<u64>, Vec<f32>), Error> {
     let mut power_table = vec![0f32; time_table.len()];
 
-    for (_, signal) in signals.iter() {
+    let num_signals = signals.len() as u64;
+    for (signal_index, (_, signal)) in signals.iter().enumerate() {
+        // periodically report progress and honour cancellation between signals
+        if signal_index % 64 == 0 {
+            if progress::is_cancelled(stop) {
+                return Err(Error::Cancelled);
+            }
+            progress::report(progress, Phase::GenTrace, signal_index as u64, num_signals, "");
+        }
         let mut prev_value: Option<wellen::SignalValue> = None;
         for (time_index, new_value) in signal.iter_changes() {
             if let Some(prev_value) = prev_value {
                 // we have a previous value, compute the power
-                power_table[time_index as usize] += power_model(&prev_value, &new_value);
+                power_table[time_index as usize] += model.apply(&prev_value, &new_value);
             }
             prev_value = Some(new_value);
         }
         // debug!("{}: {}", s.full_name(&hierarchy), signal.size_in_memory());
     }
+    progress::report(progress, Phase::GenTrace, num_signals, num_signals, "");
     let mut leftover = 0.0;
 
     let (left, right): (Vec<u64>, Vec<f32>) = time_table
@@ -190,7 +365,18 @@ pub fn wave_to_powertrace<F: Fn(&(&u64, f32)) -> bool, P: AsRef<Path>>(
     show_progress: bool,
     filter_predicate: F,
     do_filter: bool,
-) -> Result<(Vec<u64>, Vec<f32>), wellen::WellenError> {
-    let (signals, time_table) = load_waveform(filename, multi_thread, show_progress)?;
-    generate_power_trace(&signals, &time_table, filter_predicate, do_filter)
+    model: LeakageModel,
+    progress: &ProgressSender,
+    stop: &StopReceiver,
+) -> Result<(Vec<u64>, Vec<f32>), Error> {
+    let (signals, time_table) = load_waveform(filename, multi_thread, show_progress, progress, stop)?;
+    generate_power_trace(
+        &signals,
+        &time_table,
+        filter_predicate,
+        do_filter,
+        model,
+        progress,
+        stop,
+    )
 }
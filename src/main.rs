@@ -1,8 +1,9 @@
 use clap::Parser;
 
+use ndarray::Array2;
 use plotly::common::Mode;
 use plotly::{Plot, Scatter};
-use scasim::wave_to_powertrace;
+use scasim::{LeakageModel, wave_to_powertrace, write_npy, write_trs};
 
 #[derive(Parser, Debug)]
 #[command(name = "scasim-power")]
@@ -24,6 +25,18 @@ struct Args {
         default_value_t = true
     )]
     show_progress: bool,
+    #[arg(
+        long = "export-npy",
+        help = "Also write the power trace samples to a NumPy .npy array",
+        value_name = "NPY_FILE"
+    )]
+    export_npy: Option<String>,
+    #[arg(
+        long = "export-trs",
+        help = "Also write the power trace to a Riscure-style .trs trace set",
+        value_name = "TRS_FILE"
+    )]
+    export_trs: Option<String>,
 }
 
 fn main() {
@@ -35,9 +48,25 @@ fn main() {
         args.show_progress,
         |_| true,
         false,
+        LeakageModel::default(),
+        &None,
+        &None,
     )
     .expect("Failed to load and process the waveform");
 
+    // Export the computed samples to portable formats before plotting so the
+    // arrays are not thrown away with the plotly window.
+    if args.export_npy.is_some() || args.export_trs.is_some() {
+        let samples = Array2::from_shape_vec((1, power_table.len()), power_table.clone())
+            .expect("Failed to shape power trace for export");
+        if let Some(path) = &args.export_npy {
+            write_npy(path, &samples.row(0)).expect("Failed to write .npy export");
+        }
+        if let Some(path) = &args.export_trs {
+            write_trs(path, samples.view(), None).expect("Failed to write .trs export");
+        }
+    }
+
     println!("Plotting {} time points", time_table.len());
 
     let trace1 = Scatter::new(time_table, power_table).mode(Mode::Lines);
@@ -8,9 +8,10 @@ use num_ordinal::{Ordinal, Osize};
 use plotly::{
     Plot, Scatter,
     common::{Mode, Title},
-    plotly_static,
 };
 
+use crate::render::{ChartSpec, RenderBackend, Series};
+
 pub fn plot_t_traces<D: Dimension, P: AsRef<Path>>(
     t_values: ArrayBase<OwnedRepr<f64>, D>,
     t_threshold: Option<f64>,
@@ -18,7 +19,7 @@ pub fn plot_t_traces<D: Dimension, P: AsRef<Path>>(
     output_dir: P,
     show_plots: bool,
     plots_config: &plotly::Configuration,
-    image_exporter: &mut plotly_static::StaticExporter,
+    backend: &mut dyn RenderBackend,
 ) -> miette::Result<()> {
     let threshold_lines = if let Some(t) = t_threshold {
         let pos = plotly::layout::Shape::new()
@@ -56,29 +57,34 @@ pub fn plot_t_traces<D: Dimension, P: AsRef<Path>>(
 
     let lines = threshold_lines.clone();
     let y_label = if abs_values { "|t|" } else { "t-value" };
-    let t_traces = t_values
-        .rows()
-        .into_iter()
-        .enumerate()
-        .map(|(i, order_t1_values)| {
-            let d = i + 1;
-            // map inf to 0 and also to absolute value
-            let ord_t_values = order_t1_values.map(|x| {
-                if x.is_finite() {
-                    if abs_values { x.abs() } else { *x }
-                } else {
-                    0.0
-                }
-            });
+    // The dashed threshold values fed to the image backend: one line when
+    // plotting absolute values, the symmetric pair otherwise.
+    let spec_thresholds = |t: f64| {
+        if abs_values {
+            vec![t]
+        } else {
+            vec![t, -t]
+        }
+    };
 
-            info!("Plotting t-values for d={d}");
-            //plot the t-values
-            let mut t_plot = Plot::new();
+    let mut overlay_series = Vec::new();
+    for (i, order_t1_values) in t_values.rows().into_iter().enumerate() {
+        let d = i + 1;
+        // map inf to 0 and also to absolute value
+        let ord_t_values = order_t1_values.map(|x| {
+            if x.is_finite() {
+                if abs_values { x.abs() } else { *x }
+            } else {
+                0.0
+            }
+        });
 
-            let t_trace = Scatter::from_array(
-                Array1::range(0., ord_t_values.len() as f32, 1.0),
-                ord_t_values.clone(),
-            )
+        info!("Plotting t-values for d={d}");
+        //plot the t-values
+        let mut t_plot = Plot::new();
+
+        let x_values = Array1::range(0., ord_t_values.len() as f32, 1.0);
+        let t_trace = Scatter::from_array(x_values.clone(), ord_t_values.clone())
             .mode(Mode::Lines)
             .line(
                 plotly::common::Line::new()
@@ -87,63 +93,73 @@ pub fn plot_t_traces<D: Dimension, P: AsRef<Path>>(
             )
             .x_axis(y_label)
             .y_axis("time (cycles)");
-            t_plot.add_trace(t_trace.clone());
-            let y_axis = plotly::layout::Axis::new().title(Title::with_text(y_label));
-            // y_max is: if t_threshold is Some(t) => Some(v) where v is the maximum if t and the absolute value of the t-values, otherwise its None
-            let max_y = if let Some(t) = t_threshold {
-                Some(
-                    ord_t_values
-                        .iter()
-                        .fold(t, |acc, &x| acc.max(x.abs()))
-                        .max(1.5 * t)
-                        + 0.5,
-                )
-            } else {
-                None
-            };
-            log::info!(
-                "Max y for d={d}: {}",
-                max_y.map_or("None".to_string(), |v| v.to_string())
-            );
-            let y_axis = if let Some(max_y) = max_y {
-                y_axis
-                    .range(vec![if abs_values { 0.0 } else { -max_y }, max_y])
-                    .auto_range(false)
-            } else {
-                y_axis.auto_range(true)
-            };
-            t_plot.set_layout(
-                plotly::Layout::new()
-                    .shapes(lines.clone())
-                    .x_axis(plotly::layout::Axis::new().title("Time (cycles)"))
-                    .y_axis(y_axis),
-            );
+        t_plot.add_trace(t_trace.clone());
+        let y_axis = plotly::layout::Axis::new().title(Title::with_text(y_label));
+        // y_max is: if t_threshold is Some(t) => Some(v) where v is the maximum if t and the absolute value of the t-values, otherwise its None
+        let max_y = if let Some(t) = t_threshold {
+            Some(
+                ord_t_values
+                    .iter()
+                    .fold(t, |acc, &x| acc.max(x.abs()))
+                    .max(1.5 * t)
+                    + 0.5,
+            )
+        } else {
+            None
+        };
+        log::info!(
+            "Max y for d={d}: {}",
+            max_y.map_or("None".to_string(), |v| v.to_string())
+        );
+        let y_range = max_y.map(|max_y| (if abs_values { 0.0 } else { -max_y }, max_y));
+        let y_axis = if let Some((lo, hi)) = y_range {
+            y_axis.range(vec![lo, hi]).auto_range(false)
+        } else {
+            y_axis.auto_range(true)
+        };
+        t_plot.set_layout(
+            plotly::Layout::new()
+                .shapes(lines.clone())
+                .x_axis(plotly::layout::Axis::new().title("Time (cycles)"))
+                .y_axis(y_axis),
+        );
 
-            let file_stem = PathBuf::from(format!("t_test_d{d}"));
-            t_plot.set_configuration(plots_config.clone());
-            let html_output_path = output_dir.as_ref().join(file_stem.with_extension("html"));
-            info!("Writing t_plot to {}", html_output_path.display());
-            t_plot.write_html(html_output_path);
-            let image_output_path = output_dir.as_ref().join(file_stem.with_extension("svg"));
-            info!("Writing t_plot to {}", image_output_path.display());
-            if let Err(e) = t_plot.write_image_with_exporter(
-                image_exporter,
-                image_output_path,
-                plotly_static::ImageFormat::SVG,
-                800,
-                600,
-                1.0,
-            ) {
-                log::error!("Failed to write t_plot to PDF: {}", e);
-            }
-            let t_plot_json_path = output_dir.as_ref().join(file_stem.with_extension("json"));
-            std::fs::write(t_plot_json_path, t_plot.to_json())
-                .expect("Failed to write t_plot to JSON file");
-            if show_plots {
-                t_plot.show();
-            }
-            t_trace
-        });
+        let file_stem = PathBuf::from(format!("t_test_d{d}"));
+        t_plot.set_configuration(plots_config.clone());
+        let html_output_path = output_dir.as_ref().join(file_stem.with_extension("html"));
+        info!("Writing t_plot to {}", html_output_path.display());
+        t_plot.write_html(html_output_path);
+
+        // Rasterize the image through the selected backend (plotly or plotters).
+        let image_output_path = output_dir.as_ref().join(file_stem.with_extension("svg"));
+        info!("Writing t_plot to {}", image_output_path.display());
+        let series = Series {
+            name: format!("d={d}"),
+            x: x_values.iter().map(|&v| v as f64).collect(),
+            y: ord_t_values.to_vec(),
+        };
+        let spec = ChartSpec {
+            title: format!("t-test (d={d})"),
+            x_label: "time (cycles)".to_string(),
+            y_label: y_label.to_string(),
+            y_range,
+            thresholds: t_threshold.map(spec_thresholds).unwrap_or_default(),
+            series: vec![series],
+            width: 800,
+            height: 600,
+        };
+        if let Err(e) = backend.render(&spec, &image_output_path) {
+            log::error!("Failed to write t_plot image: {}", e);
+        }
+        overlay_series.push(spec.series.into_iter().next().unwrap());
+
+        let t_plot_json_path = output_dir.as_ref().join(file_stem.with_extension("json"));
+        std::fs::write(t_plot_json_path, t_plot.to_json())
+            .expect("Failed to write t_plot to JSON file");
+        if show_plots {
+            t_plot.show();
+        }
+    }
     let t_plots_file_stem = PathBuf::from("all_t_values");
     let mut all_t_plot = Plot::new();
     all_t_plot.set_configuration(plots_config.clone());
@@ -153,15 +169,36 @@ pub fn plot_t_traces<D: Dimension, P: AsRef<Path>>(
             .y_axis(plotly::layout::Axis::new().title(Title::with_text(y_label)))
             .shapes(threshold_lines),
     );
-    for (i, t_trace) in t_traces.enumerate() {
-        let d = i + 1;
-        all_t_plot.add_trace(t_trace.name(format!("d={d}")));
+    for series in &overlay_series {
+        all_t_plot.add_trace(
+            Scatter::new(series.x.clone(), series.y.clone())
+                .mode(Mode::Lines)
+                .name(series.name.clone()),
+        );
     }
     let html_output_path = output_dir
         .as_ref()
         .join(t_plots_file_stem.with_extension("html"));
     info!("Writing all_t_plot to {}", html_output_path.display());
-    all_t_plot.write_html(html_output_path);
+    all_t_plot.write_html(&html_output_path);
+
+    // Draw the combined overlay image through the backend too.
+    let overlay_image_path = output_dir
+        .as_ref()
+        .join(t_plots_file_stem.with_extension("svg"));
+    let overlay_spec = ChartSpec {
+        title: "all t-values".to_string(),
+        x_label: "time (cycles)".to_string(),
+        y_label: y_label.to_string(),
+        y_range: None,
+        thresholds: t_threshold.map(spec_thresholds).unwrap_or_default(),
+        series: overlay_series,
+        width: 1000,
+        height: 600,
+    };
+    if let Err(e) = backend.render(&overlay_spec, &overlay_image_path) {
+        log::error!("Failed to write all_t_values image: {}", e);
+    }
     if show_plots {
         all_t_plot.show();
     }
@@ -175,7 +212,7 @@ pub fn plot_max_t_values(
     output_dir: &Path,
     show_plots: bool,
     plots_config: &plotly::Configuration,
-    image_exporter: &mut plotly_static::StaticExporter,
+    backend: &mut dyn RenderBackend,
 ) -> miette::Result<()> {
     assert!(num_traces_so_far.len() == max_t_values[0].len());
 
@@ -205,6 +242,8 @@ pub fn plot_max_t_values(
             .y_axis(plotly::layout::Axis::new().title("max(|t|)"))
             .shapes(threshold_line.into_iter().collect()),
     );
+    let traces_x: Vec<f64> = num_traces_so_far.iter().map(|&n| n as f64).collect();
+    let mut image_series = Vec::new();
     for (i, max_tvals) in max_t_values.into_iter().enumerate() {
         let d = i + 1;
         println!(
@@ -214,7 +253,7 @@ pub fn plot_max_t_values(
                 .max_by(|a, b| a.partial_cmp(b).unwrap())
                 .unwrap_or(&0.0)
         );
-        let max_t_trace = Scatter::new(num_traces_so_far.clone(), max_tvals)
+        let max_t_trace = Scatter::new(num_traces_so_far.clone(), max_tvals.clone())
             .mode(Mode::Lines)
             .name(format!("d={d}"))
             .line(
@@ -223,6 +262,11 @@ pub fn plot_max_t_values(
                     .auto_color_scale(true),
             );
         max_t_plot.add_trace(max_t_trace);
+        image_series.push(Series {
+            name: format!("d={d}"),
+            x: traces_x.clone(),
+            y: max_tvals,
+        });
     }
     let max_t_plot_file_stem = PathBuf::from("max_t_values");
     let max_t_plot_file_stem = output_dir.join(max_t_plot_file_stem);
@@ -240,19 +284,20 @@ pub fn plot_max_t_values(
     info!("Writing max_t_plot to {}", max_t_plot_json_path.display());
     std::fs::write(max_t_plot_json_path, max_t_plot.to_json())
         .expect("Failed to write max_t_plot to JSON file");
-    info!(
-        "Writing max_t_plot to {}",
-        max_t_plot_file_stem.with_extension("svg").display()
-    );
-    if let Err(e) = max_t_plot.write_image_with_exporter(
-        image_exporter,
-        max_t_plot_file_stem.with_extension("svg"),
-        plotly_static::ImageFormat::SVG,
-        800,
-        600,
-        1.0,
-    ) {
-        log::error!("Failed to write max_t_plot to SVG: {}", e);
+    let svg_output_path = max_t_plot_file_stem.with_extension("svg");
+    info!("Writing max_t_plot to {}", svg_output_path.display());
+    let spec = ChartSpec {
+        title: "max |t| vs number of traces".to_string(),
+        x_label: "Number of Traces".to_string(),
+        y_label: "max(|t|)".to_string(),
+        y_range: None,
+        thresholds: t_threshold.into_iter().collect(),
+        series: image_series,
+        width: 800,
+        height: 600,
+    };
+    if let Err(e) = backend.render(&spec, &svg_output_path) {
+        log::error!("Failed to write max_t_plot image: {}", e);
     }
     if show_plots {
         max_t_plot.show();
@@ -6,10 +6,51 @@ pub trait Hamming {
     fn hamming_distance(&self, other: &Self) -> u32;
 }
 
+/// Selectable leakage model used to turn signal transitions into per-sample
+/// power. All variants are built on the [`Hamming`] primitive so any backing
+/// value type (e.g. [`wellen::SignalValue`]) works unchanged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LeakageModel {
+    /// Hamming distance between the previous and new value (pure transition
+    /// counting, the original behaviour).
+    HammingDistance,
+    /// Hamming weight of the new value.
+    HammingWeight,
+    /// Weighted combination `static_weight * HW(new) + dynamic_weight * HD(prev, new)`,
+    /// modelling static (leakage) and dynamic (switching) power respectively.
+    Weighted {
+        static_weight: f32,
+        dynamic_weight: f32,
+    },
+}
+
+impl Default for LeakageModel {
+    fn default() -> Self {
+        LeakageModel::HammingDistance
+    }
+}
+
+impl LeakageModel {
+    /// Evaluate the model for a transition from `prev_value` to `new_value`.
+    #[inline(always)]
+    pub fn apply<V: Hamming>(&self, prev_value: &V, new_value: &V) -> f32 {
+        match self {
+            LeakageModel::HammingDistance => new_value.hamming_distance(prev_value) as f32,
+            LeakageModel::HammingWeight => new_value.hamming_weight() as f32,
+            LeakageModel::Weighted {
+                static_weight,
+                dynamic_weight,
+            } => {
+                static_weight * new_value.hamming_weight() as f32
+                    + dynamic_weight * new_value.hamming_distance(prev_value) as f32
+            }
+        }
+    }
+}
+
 #[inline(always)]
 pub fn power_model<V: Hamming>(prev_value: &V, new_value: &V) -> f32 {
-    // new_value.hamming_weight() as f32 * 0.1 + // static power
-    new_value.hamming_distance(&prev_value) as f32
+    LeakageModel::default().apply(prev_value, new_value)
 }
 
 impl<'a> Hamming for wellen::SignalValue<'a> {
@@ -0,0 +1,78 @@
+use crossbeam_channel::Sender;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The phase a long-running operation is currently in. A front-end can use this
+/// to label a unified progress bar as work moves from loading a waveform all the
+/// way through to folding traces into the t-test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    LoadHeader,
+    LoadBody,
+    LoadSignals,
+    GenTrace,
+    TTest,
+}
+
+/// A snapshot of progress, sent over a channel so a CLI progress bar or GUI can
+/// render a unified view across every phase of a run.
+#[derive(Debug, Clone)]
+pub struct ProgressData {
+    pub phase: Phase,
+    /// Number of items processed so far within the current phase.
+    pub done: u64,
+    /// Total number of items in the current phase (`0` if unknown).
+    pub total: u64,
+    /// The file currently being processed.
+    pub filename: String,
+}
+
+/// Optional sink for [`ProgressData`] snapshots.
+pub type ProgressSender = Option<Sender<ProgressData>>;
+/// Optional stop signal. Once the flag is set the current operation aborts
+/// cleanly with [`Error::Cancelled`]. A shared [`AtomicBool`] rather than a
+/// channel so a single raise is observed by every thread that polls it (a
+/// consumed channel message would only ever reach one receiver).
+pub type StopReceiver = Option<Arc<AtomicBool>>;
+
+/// Error returned by the progress-aware loading and processing functions.
+#[derive(Debug)]
+pub enum Error {
+    Wellen(wellen::WellenError),
+    /// The operation was cancelled via the stop signal.
+    Cancelled,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Wellen(e) => write!(f, "{e}"),
+            Error::Cancelled => write!(f, "operation cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<wellen::WellenError> for Error {
+    fn from(e: wellen::WellenError) -> Self {
+        Error::Wellen(e)
+    }
+}
+
+/// Returns `true` if the stop signal has been raised.
+pub fn is_cancelled(stop: &StopReceiver) -> bool {
+    stop.as_ref().map_or(false, |flag| flag.load(Ordering::Relaxed))
+}
+
+/// Send a progress snapshot if a sender is present, ignoring disconnects.
+pub fn report(progress: &ProgressSender, phase: Phase, done: u64, total: u64, filename: &str) {
+    if let Some(tx) = progress {
+        let _ = tx.send(ProgressData {
+            phase,
+            done,
+            total,
+            filename: filename.to_string(),
+        });
+    }
+}
@@ -0,0 +1,270 @@
+//! Pluggable image-rendering backends for the plotting subsystem.
+//!
+//! [`plot`](crate::plot) builds interactive `plotly` HTML/JSON artifacts, but
+//! rasterizing them to SVG/PNG normally goes through `plotly_static`, which
+//! drives a headless browser engine — fragile on CI boxes and unavailable on
+//! air-gapped lab machines. This module abstracts the image step behind a
+//! [`RenderBackend`] so the same chart can be drawn either with `plotly`
+//! (browser) or the pure-Rust [`plotters`] crate (no browser).
+//!
+//! A backend consumes a neutral [`ChartSpec`] rather than a `plotly` object, so
+//! the two implementations stay independent.
+
+use std::path::Path;
+
+use miette::{IntoDiagnostic, Result};
+use plotly::plotly_static;
+
+/// A single named line series.
+pub struct Series {
+    pub name: String,
+    pub x: Vec<f64>,
+    pub y: Vec<f64>,
+}
+
+/// A backend-neutral description of a line chart: the data, axis labels, an
+/// optional fixed y-range and any number of dashed red threshold lines.
+pub struct ChartSpec {
+    pub title: String,
+    pub x_label: String,
+    pub y_label: String,
+    /// Fixed `(min, max)` y-range, or `None` to auto-range.
+    pub y_range: Option<(f64, f64)>,
+    /// Horizontal lines drawn dashed and red (e.g. `±4.5`).
+    pub thresholds: Vec<f64>,
+    pub series: Vec<Series>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ChartSpec {
+    /// Largest x across all series, used to span threshold lines and axes.
+    fn x_max(&self) -> f64 {
+        self.series
+            .iter()
+            .flat_map(|s| s.x.iter().copied())
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Renders a [`ChartSpec`] to an image file. The output format is taken from the
+/// destination extension (`svg` or `png`).
+pub trait RenderBackend {
+    fn render(&mut self, spec: &ChartSpec, path: &Path) -> Result<()>;
+}
+
+/// Selects which [`RenderBackend`] to build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderBackendKind {
+    /// Rasterize through `plotly_static` (requires a headless browser engine).
+    Plotly,
+    /// Draw directly with the pure-Rust `plotters` crate (no browser).
+    Plotters,
+}
+
+impl RenderBackendKind {
+    /// Build a boxed backend. The `plots_config` is only consulted by the
+    /// `plotly` backend.
+    pub fn build(self, plots_config: &plotly::Configuration) -> Result<Box<dyn RenderBackend>> {
+        match self {
+            RenderBackendKind::Plotly => Ok(Box::new(PlotlyBackend::new(plots_config.clone())?)),
+            RenderBackendKind::Plotters => Ok(Box::new(PlottersBackend)),
+        }
+    }
+}
+
+fn image_format(path: &Path) -> plotly_static::ImageFormat {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("png") => plotly_static::ImageFormat::PNG,
+        _ => plotly_static::ImageFormat::SVG,
+    }
+}
+
+/// Browser-backed backend that reuses `plotly_static`'s static exporter.
+pub struct PlotlyBackend {
+    exporter: plotly_static::StaticExporter,
+    config: plotly::Configuration,
+}
+
+impl PlotlyBackend {
+    pub fn new(config: plotly::Configuration) -> Result<Self> {
+        let exporter = plotly_static::StaticExporterBuilder::default()
+            .pdf_export_timeout(1000)
+            .build()
+            .into_diagnostic()?;
+        Ok(Self { exporter, config })
+    }
+}
+
+impl RenderBackend for PlotlyBackend {
+    fn render(&mut self, spec: &ChartSpec, path: &Path) -> Result<()> {
+        use plotly::common::{Line, Mode, Title};
+        use plotly::{Plot, Scatter};
+
+        let mut plot = Plot::new();
+        plot.set_configuration(self.config.clone());
+        for series in &spec.series {
+            plot.add_trace(
+                Scatter::new(series.x.clone(), series.y.clone())
+                    .mode(Mode::Lines)
+                    .name(series.name.clone())
+                    .line(Line::new().width(1.5).auto_color_scale(true)),
+            );
+        }
+
+        let x_max = spec.x_max();
+        let shapes = spec
+            .thresholds
+            .iter()
+            .map(|&t| {
+                plotly::layout::Shape::new()
+                    .shape_type(plotly::layout::ShapeType::Line)
+                    .x0(0)
+                    .x1(x_max)
+                    .y0(t)
+                    .y1(t)
+                    .line(
+                        plotly::layout::ShapeLine::new()
+                            .color(plotly::color::NamedColor::Red)
+                            .width(1.0)
+                            .dash(plotly::common::DashType::Dash),
+                    )
+            })
+            .collect();
+
+        let y_axis = plotly::layout::Axis::new().title(Title::with_text(spec.y_label.clone()));
+        let y_axis = match spec.y_range {
+            Some((lo, hi)) => y_axis.range(vec![lo, hi]).auto_range(false),
+            None => y_axis.auto_range(true),
+        };
+        plot.set_layout(
+            plotly::Layout::new()
+                .title(Title::with_text(spec.title.clone()))
+                .x_axis(plotly::layout::Axis::new().title(Title::with_text(spec.x_label.clone())))
+                .y_axis(y_axis)
+                .shapes(shapes),
+        );
+
+        plot.write_image_with_exporter(
+            &mut self.exporter,
+            path,
+            image_format(path),
+            spec.width as usize,
+            spec.height as usize,
+            1.0,
+        )
+        .into_diagnostic()
+    }
+}
+
+/// Pure-Rust backend that draws with [`plotters`], without spawning a browser.
+pub struct PlottersBackend;
+
+impl RenderBackend for PlottersBackend {
+    fn render(&mut self, spec: &ChartSpec, path: &Path) -> Result<()> {
+        use plotters::prelude::*;
+
+        let is_png = matches!(path.extension().and_then(|e| e.to_str()), Some("png"));
+        if is_png {
+            let root = BitMapBackend::new(path, (spec.width, spec.height)).into_drawing_area();
+            draw_chart(root, spec)
+        } else {
+            let root = SVGBackend::new(path, (spec.width, spec.height)).into_drawing_area();
+            draw_chart(root, spec)
+        }
+    }
+}
+
+/// Shared drawing routine over any `plotters` backend, so SVG and PNG share one
+/// code path.
+fn draw_chart<DB>(
+    root: plotters::drawing::DrawingArea<DB, plotters::coord::Shift>,
+    spec: &ChartSpec,
+) -> Result<()>
+where
+    DB: plotters::prelude::DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    use plotters::prelude::*;
+
+    // `plotters` error types are not `Send + Sync`, so map them through
+    // `miette!` rather than `into_diagnostic`.
+    let draw_err = |e: plotters::drawing::DrawingAreaErrorKind<DB::ErrorType>| {
+        miette::miette!("plotters render error: {e}")
+    };
+
+    root.fill(&WHITE).map_err(draw_err)?;
+
+    let x_max = spec.x_max().max(1.0);
+    let (y_lo, y_hi) = spec.y_range.unwrap_or_else(|| {
+        let mut lo = f64::INFINITY;
+        let mut hi = f64::NEG_INFINITY;
+        for s in &spec.series {
+            for &v in &s.y {
+                lo = lo.min(v);
+                hi = hi.max(v);
+            }
+        }
+        for &t in &spec.thresholds {
+            lo = lo.min(t);
+            hi = hi.max(t);
+        }
+        if !lo.is_finite() || !hi.is_finite() {
+            (0.0, 1.0)
+        } else {
+            (lo, hi)
+        }
+    });
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(&spec.title, ("sans-serif", 20))
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d(0.0..x_max, y_lo..y_hi)
+        .map_err(draw_err)?;
+
+    chart
+        .configure_mesh()
+        .x_desc(&spec.x_label)
+        .y_desc(&spec.y_label)
+        .draw()
+        .map_err(draw_err)?;
+
+    for (i, series) in spec.series.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        chart
+            .draw_series(LineSeries::new(
+                series.x.iter().copied().zip(series.y.iter().copied()),
+                color.stroke_width(2),
+            ))
+            .map_err(draw_err)?
+            .label(series.name.clone())
+            .legend(move |(x, y)| PathElement::new([(x, y), (x + 18, y)], color.stroke_width(2)));
+    }
+
+    // Threshold lines, drawn dashed and red by stitching short segments.
+    for &t in &spec.thresholds {
+        let step = (x_max / 80.0).max(1.0);
+        let mut x = 0.0;
+        while x < x_max {
+            let end = (x + step * 0.6).min(x_max);
+            chart
+                .draw_series(LineSeries::new([(x, t), (end, t)], RED.stroke_width(1)))
+                .map_err(draw_err)?;
+            x += step;
+        }
+    }
+
+    if spec.series.len() > 1 {
+        chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()
+            .map_err(draw_err)?;
+    }
+
+    root.present().map_err(draw_err)?;
+    Ok(())
+}
@@ -0,0 +1,514 @@
+//! Append-only, fixed-record trace store.
+//!
+//! The per-trace NPZ cache stores one array per trace and rebuilds a dense
+//! [`ndarray::Array2`] on every reload. This module replaces it with a single
+//! binary file of equally sized trace records plus a parallel labels region,
+//! preceded by a small fixed header. Because every record has the same size,
+//! loading an uncompressed store is an `mmap` away from a zero-copy
+//! [`ArrayView2`] with O(1) random access, and new captures can be appended to
+//! it without rewriting the trace data.
+
+use crate::LeakageModel;
+use memmap2::Mmap;
+use ndarray::{ArrayView1, ArrayView2};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"SCASTOR\0";
+const VERSION: u32 = 1;
+/// Fixed header size in bytes. Kept at an 8-byte multiple so the trace data that
+/// follows is aligned for `f32` access through an `mmap`.
+const HEADER_LEN: usize = 64;
+const DTYPE_F32: u8 = 0;
+const LABEL_DTYPE_U16: u8 = 1;
+
+/// Default codec level used when a store is rewritten without an explicit one
+/// (e.g. when appending to an already-compressed store).
+pub const DEFAULT_COMPRESSION_LEVEL: i32 = 3;
+
+/// Codec used for the payload (trace records + labels) of a store.
+///
+/// [`Compression::None`] keeps the payload uncompressed so it can be read back
+/// as a zero-copy `mmap`; the compressing codecs trade that for a smaller file
+/// and are read back through an owned, decompressed buffer. The codec is stored
+/// in the header, so [`open_trace_store`] auto-detects it without the caller
+/// having to remember which one was used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    Deflate,
+    Zstd,
+}
+
+impl Compression {
+    fn tag(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Deflate => 1,
+            Compression::Zstd => 2,
+        }
+    }
+
+    fn from_tag(tag: u8) -> io::Result<Self> {
+        match tag {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Deflate),
+            2 => Ok(Compression::Zstd),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported compression codec {other} in trace store"),
+            )),
+        }
+    }
+
+    fn encode(self, payload: &[u8], level: i32) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(payload.to_vec()),
+            Compression::Deflate => {
+                use flate2::{Compression as FlateLevel, write::ZlibEncoder};
+                let mut enc = ZlibEncoder::new(Vec::new(), FlateLevel::new(level.clamp(0, 9) as u32));
+                enc.write_all(payload)?;
+                enc.finish()
+            }
+            Compression::Zstd => zstd::stream::encode_all(payload, level),
+        }
+    }
+
+    fn decode(self, payload: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(payload.to_vec()),
+            Compression::Deflate => {
+                use flate2::read::ZlibDecoder;
+                let mut out = Vec::new();
+                ZlibDecoder::new(payload).read_to_end(&mut out)?;
+                Ok(out)
+            }
+            Compression::Zstd => zstd::stream::decode_all(payload),
+        }
+    }
+}
+
+/// Metadata describing a trace store, including the power-model parameters used
+/// to generate it so a cache can be invalidated when they change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Meta {
+    pub samples_per_trace: usize,
+    pub trace_count: usize,
+    pub model: LeakageModel,
+}
+
+fn model_fields(model: &LeakageModel) -> (u8, f32, f32) {
+    match *model {
+        LeakageModel::HammingDistance => (0, 0.0, 0.0),
+        LeakageModel::HammingWeight => (1, 0.0, 0.0),
+        LeakageModel::Weighted {
+            static_weight,
+            dynamic_weight,
+        } => (2, static_weight, dynamic_weight),
+    }
+}
+
+fn model_from_fields(tag: u8, static_weight: f32, dynamic_weight: f32) -> LeakageModel {
+    match tag {
+        1 => LeakageModel::HammingWeight,
+        2 => LeakageModel::Weighted {
+            static_weight,
+            dynamic_weight,
+        },
+        _ => LeakageModel::HammingDistance,
+    }
+}
+
+fn encode_header(meta: &Meta, compression: Compression) -> [u8; HEADER_LEN] {
+    let mut buf = [0u8; HEADER_LEN];
+    let (tag, sw, dw) = model_fields(&meta.model);
+    buf[0..8].copy_from_slice(MAGIC);
+    buf[8..12].copy_from_slice(&VERSION.to_le_bytes());
+    buf[12] = DTYPE_F32;
+    buf[13] = LABEL_DTYPE_U16;
+    buf[14] = tag;
+    buf[15] = compression.tag();
+    buf[16..24].copy_from_slice(&(meta.samples_per_trace as u64).to_le_bytes());
+    buf[24..32].copy_from_slice(&(meta.trace_count as u64).to_le_bytes());
+    buf[32..36].copy_from_slice(&sw.to_le_bytes());
+    buf[36..40].copy_from_slice(&dw.to_le_bytes());
+    buf
+}
+
+fn decode_header(buf: &[u8]) -> io::Result<(Meta, Compression)> {
+    if buf.len() < HEADER_LEN || &buf[0..8] != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a scasim trace store",
+        ));
+    }
+    let version = u32::from_le_bytes(buf[8..12].try_into().unwrap());
+    if version != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported trace store version {version}"),
+        ));
+    }
+    if buf[12] != DTYPE_F32 || buf[13] != LABEL_DTYPE_U16 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unsupported dtype in trace store",
+        ));
+    }
+    let samples_per_trace = u64::from_le_bytes(buf[16..24].try_into().unwrap()) as usize;
+    let trace_count = u64::from_le_bytes(buf[24..32].try_into().unwrap()) as usize;
+    let sw = f32::from_le_bytes(buf[32..36].try_into().unwrap());
+    let dw = f32::from_le_bytes(buf[36..40].try_into().unwrap());
+    let compression = Compression::from_tag(buf[15])?;
+    Ok((
+        Meta {
+            samples_per_trace,
+            trace_count,
+            model: model_from_fields(buf[14], sw, dw),
+        },
+        compression,
+    ))
+}
+
+/// Serialize the trace records followed by the labels region into one
+/// contiguous byte payload, ready to be written raw or through a codec.
+fn build_payload(traces: ArrayView2<f32>, labels: ArrayView1<u16>) -> Vec<u8> {
+    let (trace_count, samples_per_trace) = traces.dim();
+    let mut payload = Vec::with_capacity(trace_count * samples_per_trace * 4 + labels.len() * 2);
+    let standard = traces.as_standard_layout();
+    for &v in standard.iter() {
+        payload.extend_from_slice(&v.to_le_bytes());
+    }
+    for &l in labels.iter() {
+        payload.extend_from_slice(&l.to_le_bytes());
+    }
+    payload
+}
+
+/// Write `traces` and `labels` to a new trace store at `path`, overwriting any
+/// existing file. `meta.model` records the power model they were generated with.
+///
+/// With [`Compression::None`] the payload is written raw so it can be mapped
+/// back with zero copies; the compressing codecs use `level` (clamped to each
+/// codec's valid range) and shrink the file at the cost of a decode on load.
+pub fn write_trace_store<P: AsRef<Path>>(
+    path: P,
+    traces: ArrayView2<f32>,
+    labels: ArrayView1<u16>,
+    meta: Meta,
+    compression: Compression,
+    level: i32,
+) -> io::Result<()> {
+    let (trace_count, samples_per_trace) = traces.dim();
+    assert_eq!(
+        labels.len(),
+        trace_count,
+        "number of labels does not match number of traces"
+    );
+    let meta = Meta {
+        trace_count,
+        samples_per_trace,
+        model: meta.model,
+    };
+
+    let mut file = io::BufWriter::new(File::create(path)?);
+    file.write_all(&encode_header(&meta, compression))?;
+    match compression {
+        // Stream the raw payload out record by record to avoid buffering a
+        // second copy of the (large) trace set before writing.
+        Compression::None => {
+            let standard = traces.as_standard_layout();
+            for &v in standard.iter() {
+                file.write_all(&v.to_le_bytes())?;
+            }
+            for &l in labels.iter() {
+                file.write_all(&l.to_le_bytes())?;
+            }
+        }
+        _ => {
+            let payload = compression.encode(&build_payload(traces, labels), level)?;
+            file.write_all(&payload)?;
+        }
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Append `traces`/`labels` to an existing store, extending it and bumping the
+/// trace count in the header. If the store does not yet exist it is created.
+///
+/// For an uncompressed store the trace data stays contiguous, so the (small)
+/// labels region is rewritten after the new records and trace bytes already on
+/// disk are never moved. A compressed store is read back, concatenated and
+/// rewritten in full with the same codec at [`DEFAULT_COMPRESSION_LEVEL`].
+pub fn append_trace_store<P: AsRef<Path>>(
+    path: P,
+    traces: ArrayView2<f32>,
+    labels: ArrayView1<u16>,
+    model: LeakageModel,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return write_trace_store(
+            path,
+            traces,
+            labels,
+            Meta {
+                samples_per_trace: traces.ncols(),
+                trace_count: traces.nrows(),
+                model,
+            },
+            Compression::None,
+            0,
+        );
+    }
+
+    // Read the existing header and labels (cheap: one u16 per trace).
+    let mut header = [0u8; HEADER_LEN];
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    file.read_exact(&mut header)?;
+    let (old, compression) = decode_header(&header)?;
+    if compression != Compression::None {
+        // A compressed payload cannot be extended in place; read it back,
+        // concatenate, and rewrite with the same codec.
+        drop(file);
+        let store = open_trace_store(path)?;
+        let mut combined_traces = store.traces().to_owned();
+        combined_traces
+            .append(ndarray::Axis(0), traces)
+            .expect("cannot append traces with a different sample count");
+        let combined_labels =
+            ndarray::concatenate(ndarray::Axis(0), &[store.labels(), labels])
+                .expect("failed to concatenate labels");
+        return write_trace_store(
+            path,
+            combined_traces.view(),
+            combined_labels.view(),
+            Meta {
+                samples_per_trace: combined_traces.ncols(),
+                trace_count: combined_traces.nrows(),
+                model,
+            },
+            compression,
+            DEFAULT_COMPRESSION_LEVEL,
+        );
+    }
+    assert_eq!(
+        old.samples_per_trace,
+        traces.ncols(),
+        "cannot append traces with a different sample count"
+    );
+
+    let data_end = HEADER_LEN as u64 + (old.trace_count * old.samples_per_trace * 4) as u64;
+    let mut old_labels = vec![0u8; old.trace_count * 2];
+    file.seek(SeekFrom::Start(data_end))?;
+    file.read_exact(&mut old_labels)?;
+
+    // Overwrite starting at the end of the existing trace data with the new
+    // trace records followed by the combined labels region.
+    file.seek(SeekFrom::Start(data_end))?;
+    let standard = traces.as_standard_layout();
+    for &v in standard.iter() {
+        file.write_all(&v.to_le_bytes())?;
+    }
+    file.write_all(&old_labels)?;
+    for &l in labels.iter() {
+        file.write_all(&l.to_le_bytes())?;
+    }
+
+    let new_count = old.trace_count + traces.nrows();
+    let new_meta = Meta {
+        trace_count: new_count,
+        ..old
+    };
+    let new_size = HEADER_LEN as u64
+        + (new_count * old.samples_per_trace * 4) as u64
+        + (new_count * 2) as u64;
+    file.set_len(new_size)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&encode_header(&new_meta, Compression::None))?;
+    file.flush()?;
+    Ok(())
+}
+
+/// A decompressed payload held in a 4-byte-aligned allocation.
+///
+/// A plain `Vec<u8>` from the decoders carries no alignment guarantee, so the
+/// zero-copy `bytemuck::cast_slice` to `f32`/`u16` in [`TraceStore::traces`]/
+/// [`TraceStore::labels`] could panic on a misaligned start. Backing the bytes
+/// with a `Vec<u32>` (which is always `f32`-aligned) keeps those casts infallible
+/// — the label region starts at a 4-byte-aligned offset, so its `u16` cast is
+/// aligned too.
+struct AlignedBytes {
+    words: Vec<u32>,
+    len: usize,
+}
+
+impl AlignedBytes {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let mut words = vec![0u32; bytes.len().div_ceil(4)];
+        bytemuck::cast_slice_mut::<u32, u8>(&mut words)[..bytes.len()].copy_from_slice(bytes);
+        Self {
+            words,
+            len: bytes.len(),
+        }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &bytemuck::cast_slice::<u32, u8>(&self.words)[..self.len]
+    }
+}
+
+/// Backing bytes for an opened store. An uncompressed store keeps the file
+/// memory-mapped so views are zero-copy; a compressed one decodes its payload
+/// into an owned, 4-byte-aligned buffer once on open.
+enum Backing {
+    Mapped(Mmap),
+    Owned(AlignedBytes),
+}
+
+/// An opened trace store. Owns its backing bytes so the [`ArrayView`]s handed
+/// out by [`TraceStore::traces`]/[`TraceStore::labels`] remain valid for its
+/// lifetime. For an uncompressed store these views are zero-copy over the
+/// `mmap`; for a compressed one they borrow the decompressed payload.
+pub struct TraceStore {
+    backing: Backing,
+    meta: Meta,
+}
+
+impl TraceStore {
+    pub fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    /// The trace+labels payload, without the fixed header.
+    fn payload(&self) -> &[u8] {
+        match &self.backing {
+            Backing::Mapped(mmap) => &mmap[HEADER_LEN..],
+            Backing::Owned(buf) => buf.as_slice(),
+        }
+    }
+
+    /// View of the trace matrix, `trace_count` rows by `samples_per_trace`
+    /// columns. Zero-copy for an uncompressed store.
+    pub fn traces(&self) -> ArrayView2<f32> {
+        let len = self.meta.trace_count * self.meta.samples_per_trace;
+        let bytes = &self.payload()[..len * 4];
+        let floats = bytemuck::cast_slice::<u8, f32>(bytes);
+        ArrayView2::from_shape((self.meta.trace_count, self.meta.samples_per_trace), floats)
+            .expect("trace store dimensions do not match file length")
+    }
+
+    /// View of the parallel labels region.
+    pub fn labels(&self) -> ArrayView1<u16> {
+        let start = self.meta.trace_count * self.meta.samples_per_trace * 4;
+        let bytes = &self.payload()[start..start + self.meta.trace_count * 2];
+        let labels = bytemuck::cast_slice::<u8, u16>(bytes);
+        ArrayView1::from_shape(self.meta.trace_count, labels)
+            .expect("label region does not match trace count")
+    }
+}
+
+/// Open the trace store at `path`, returning a handle from which the traces,
+/// labels and metadata can be read. The codec recorded in the header decides
+/// whether the payload is mapped directly or decompressed first, so callers do
+/// not need to know which codec was used to write it.
+pub fn open_trace_store<P: AsRef<Path>>(path: P) -> io::Result<TraceStore> {
+    let file = File::open(path)?;
+    let mmap = unsafe { Mmap::map(&file)? };
+    let (meta, compression) = decode_header(&mmap)?;
+    let backing = match compression {
+        Compression::None => Backing::Mapped(mmap),
+        _ => Backing::Owned(AlignedBytes::from_bytes(
+            &compression.decode(&mmap[HEADER_LEN..])?,
+        )),
+    };
+    Ok(TraceStore { backing, meta })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{Array1, Array2, array};
+
+    /// Unique scratch path under the system temp dir for a single test.
+    fn scratch(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("scasim-store-{}-{}.store", std::process::id(), name))
+    }
+
+    fn sample() -> (Array2<f32>, Array1<u16>) {
+        let traces = array![[0.0f32, 1.0, 2.0], [3.0, 4.0, 5.0], [6.0, 7.0, 8.0]];
+        let labels = array![0u16, 1, 0];
+        (traces, labels)
+    }
+
+    #[test]
+    fn write_open_round_trip_all_codecs() {
+        let (traces, labels) = sample();
+        for codec in [Compression::None, Compression::Deflate, Compression::Zstd] {
+            let path = scratch(&format!("roundtrip-{codec:?}"));
+            write_trace_store(
+                &path,
+                traces.view(),
+                labels.view(),
+                Meta {
+                    samples_per_trace: traces.ncols(),
+                    trace_count: traces.nrows(),
+                    model: LeakageModel::HammingDistance,
+                },
+                codec,
+                DEFAULT_COMPRESSION_LEVEL,
+            )
+            .unwrap();
+
+            let store = open_trace_store(&path).unwrap();
+            assert_eq!(store.traces(), traces, "traces differ for {codec:?}");
+            assert_eq!(store.labels(), labels, "labels differ for {codec:?}");
+            std::fs::remove_file(&path).ok();
+        }
+    }
+
+    #[test]
+    fn append_extends_store() {
+        let (traces, labels) = sample();
+        let extra = array![[9.0f32, 10.0, 11.0]];
+        let extra_labels = array![1u16];
+        for codec in [Compression::None, Compression::Zstd] {
+            let path = scratch(&format!("append-{codec:?}"));
+            write_trace_store(
+                &path,
+                traces.view(),
+                labels.view(),
+                Meta {
+                    samples_per_trace: traces.ncols(),
+                    trace_count: traces.nrows(),
+                    model: LeakageModel::HammingDistance,
+                },
+                codec,
+                DEFAULT_COMPRESSION_LEVEL,
+            )
+            .unwrap();
+
+            append_trace_store(
+                &path,
+                extra.view(),
+                extra_labels.view(),
+                LeakageModel::HammingDistance,
+            )
+            .unwrap();
+
+            let store = open_trace_store(&path).unwrap();
+            let expected_traces =
+                ndarray::concatenate(ndarray::Axis(0), &[traces.view(), extra.view()]).unwrap();
+            let expected_labels =
+                ndarray::concatenate(ndarray::Axis(0), &[labels.view(), extra_labels.view()])
+                    .unwrap();
+            assert_eq!(store.meta().trace_count, 4);
+            assert_eq!(store.traces(), expected_traces, "traces differ for {codec:?}");
+            assert_eq!(store.labels(), expected_labels, "labels differ for {codec:?}");
+            std::fs::remove_file(&path).ok();
+        }
+    }
+}